@@ -0,0 +1,15 @@
+use crate::api::{
+    Connection, Error, Location, SearchConnectionRequest, StationboardEntry, StationboardRequest,
+};
+
+pub trait TimetableBackend: Send + Sync {
+    fn search_location(&self, query: &str) -> Result<Vec<Location>, Error>;
+
+    /// The stations closest to the WGS84 position, the nearest first.
+    fn search_nearby(&self, latitude: f64, longitude: f64) -> Result<Vec<Location>, Error>;
+
+    fn search_connection(&self, request: SearchConnectionRequest)
+        -> Result<Vec<Connection>, Error>;
+
+    fn stationboard(&self, request: StationboardRequest) -> Result<Vec<StationboardEntry>, Error>;
+}
@@ -0,0 +1,145 @@
+use chrono::{DateTime, Duration, FixedOffset};
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%z";
+
+/// Deserializes an optional timestamp like `2021-03-15T07:35:00+0100`.
+pub fn deserialize_optional_date_time<'de, D>(
+    deserializer: D,
+) -> Result<Option<DateTime<FixedOffset>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(text) => parse_date_time(&text).map(Some).map_err(de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+/// Deserializes a duration like `00d01:47:00` (days, hours, minutes, seconds).
+pub fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = String::deserialize(deserializer)?;
+    parse_duration(&text).map_err(de::Error::custom)
+}
+
+/// Serializes an optional timestamp in the same format the API uses.
+pub fn serialize_optional_date_time<S>(
+    time: &Option<DateTime<FixedOffset>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    time.map(|t| t.format(DATE_TIME_FORMAT).to_string())
+        .serialize(serializer)
+}
+
+/// Serializes a duration in the same format the API uses.
+pub fn serialize_duration<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    format_duration(duration).serialize(serializer)
+}
+
+fn parse_date_time(text: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_str(text, DATE_TIME_FORMAT)
+        .map_err(|e| format!("invalid timestamp \"{}\": {}", text, e))
+}
+
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration \"{}\"", text);
+
+    let (days, time) = match text.find('d') {
+        Some(pos) => (&text[..pos], &text[pos + 1..]),
+        None => return Err(invalid()),
+    };
+
+    let parts = time
+        .split(':')
+        .map(|part| part.parse::<i64>())
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|_| invalid())?;
+
+    let days = days.parse::<i64>().map_err(|_| invalid())?;
+
+    match parts.as_slice() {
+        [hours, minutes, seconds] => Ok(Duration::days(days)
+            + Duration::hours(*hours)
+            + Duration::minutes(*minutes)
+            + Duration::seconds(*seconds)),
+        _ => Err(invalid()),
+    }
+}
+
+fn format_duration(duration: &Duration) -> String {
+    format!(
+        "{:02}d{:02}:{:02}:{:02}",
+        duration.num_days(),
+        duration.num_hours() % 24,
+        duration.num_minutes() % 60,
+        duration.num_seconds() % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Error, Stop};
+
+    #[test]
+    fn it_parses_a_duration_with_days() {
+        let duration = parse_duration("01d02:03:04").unwrap();
+        assert_eq!(duration.num_seconds(), 86400 + 2 * 3600 + 3 * 60 + 4);
+    }
+
+    #[test]
+    fn it_parses_a_duration_without_days() {
+        let duration = parse_duration("00d01:47:00").unwrap();
+        assert_eq!(duration.num_minutes(), 107);
+    }
+
+    #[test]
+    fn it_rejects_a_malformed_duration() {
+        assert!(parse_duration("01:47:00").is_err());
+        assert!(parse_duration("00d01:47").is_err());
+        assert!(parse_duration("00dxx:47:00").is_err());
+    }
+
+    #[test]
+    fn it_formats_a_duration_like_the_api() {
+        let duration = parse_duration("01d02:03:04").unwrap();
+        assert_eq!(format_duration(&duration), "01d02:03:04");
+    }
+
+    #[test]
+    fn it_parses_a_timestamp_with_offset() {
+        let time = parse_date_time("2021-03-15T07:35:00+0100").unwrap();
+        assert_eq!(time.format("%H:%M").to_string(), "07:35");
+        assert_eq!(time.offset().local_minus_utc(), 3600);
+    }
+
+    #[test]
+    fn it_rejects_a_malformed_timestamp() {
+        assert!(parse_date_time("2021-03-15 07:35").is_err());
+    }
+
+    #[test]
+    fn it_reports_a_malformed_timestamp_as_schema_error() {
+        let json = r#"{"station": {"name": "Zug"}, "departure": "07:35"}"#;
+        let error = Error::from(serde_json::from_str::<Stop>(json).unwrap_err());
+        assert!(matches!(error, Error::Schema(_)));
+    }
+
+    #[test]
+    fn it_accepts_a_missing_timestamp() {
+        let json = r#"{"station": {"name": "Zug"}, "arrival": null}"#;
+        let stop = serde_json::from_str::<Stop>(json).unwrap();
+        assert!(stop.arrival.is_none());
+        assert!(stop.departure.is_none());
+    }
+}
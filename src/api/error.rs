@@ -0,0 +1,90 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    NoNetwork(reqwest::Error),
+    Timeout(reqwest::Error),
+    Status(u16),
+    Http(reqwest::Error),
+    Schema(serde_json::Error),
+    UnknownStation(String),
+    AmbiguousStation {
+        query: String,
+        candidates: Vec<String>,
+    },
+    NoConnections,
+    Io(io::Error),
+}
+
+impl Error {
+    /// Whether repeating the same request unchanged might succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::NoNetwork(_) | Error::Timeout(_) | Error::Http(_) => true,
+            Error::Status(status) => *status >= 500 || *status == 429,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoNetwork(_) => write!(f, "the timetable service could not be reached"),
+            Error::Timeout(_) => write!(f, "the timetable service did not respond in time"),
+            Error::Status(status) => write!(f, "the timetable service returned status {}", status),
+            Error::Http(e) => write!(f, "request failed: {}", e),
+            Error::Schema(e) => write!(f, "response has an unexpected format: {}", e),
+            Error::UnknownStation(name) => write!(f, "no station named \"{}\" was found", name),
+            Error::AmbiguousStation { query, candidates } => write!(
+                f,
+                "\"{}\" matches several stations: {}",
+                query,
+                candidates.join(", ")
+            ),
+            Error::NoConnections => write!(f, "no connections were found"),
+            Error::Io(e) => write!(f, "could not read the response: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::NoNetwork(e) | Error::Timeout(e) | Error::Http(e) => Some(e),
+            Error::Schema(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return Error::Timeout(error);
+        }
+
+        if error.is_connect() {
+            return Error::NoNetwork(error);
+        }
+
+        match error.status() {
+            Some(status) => Error::Status(status.as_u16()),
+            None => Error::Http(error),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Schema(error)
+    }
+}
@@ -0,0 +1,431 @@
+use serde::de::DeserializeOwned;
+
+use std::env;
+use std::fs;
+use std::path;
+
+use crate::api::{
+    Connection, ConnectionsResponse, Error, Location, LocationsResponse, OpendataBackend,
+    SearchConnectionRequest, StationboardEntry, StationboardRequest, StationboardResponse,
+    TimetableBackend,
+};
+
+const RECORD_ENV_VAR: &str = "SBB_RECORD_FIXTURES";
+
+pub enum FixtureMode {
+    Replay,
+    Record,
+}
+
+pub struct FixtureBackend {
+    fixture_dir: path::PathBuf,
+    mode: FixtureMode,
+    upstream: OpendataBackend,
+}
+
+impl FixtureBackend {
+    pub fn new<P: AsRef<path::Path>>(fixture_dir: P, mode: FixtureMode) -> Self {
+        Self {
+            fixture_dir: fixture_dir.as_ref().to_path_buf(),
+            mode,
+            upstream: OpendataBackend::new(),
+        }
+    }
+
+    pub fn replay<P: AsRef<path::Path>>(fixture_dir: P) -> Self {
+        Self::new(fixture_dir, FixtureMode::Replay)
+    }
+
+    pub fn recording<P: AsRef<path::Path>>(fixture_dir: P) -> Self {
+        Self::new(fixture_dir, FixtureMode::Record)
+    }
+
+    /// Replays fixtures unless `SBB_RECORD_FIXTURES` is set, in which case
+    /// the live service is queried and the fixtures are overwritten.
+    pub fn from_env<P: AsRef<path::Path>>(fixture_dir: P) -> Self {
+        match env::var_os(RECORD_ENV_VAR) {
+            Some(_) => Self::recording(fixture_dir),
+            None => Self::replay(fixture_dir),
+        }
+    }
+
+    fn load<T, F>(&self, name: &str, fetch: F) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        F: FnOnce(&OpendataBackend) -> Result<serde_json::Value, Error>,
+    {
+        let fixture_file = self.fixture_dir.join(format!("{}.json", name));
+
+        if let FixtureMode::Record = self.mode {
+            let response = fetch(&self.upstream)?;
+            fs::create_dir_all(&self.fixture_dir)?;
+            fs::write(
+                &fixture_file,
+                serde_json::to_string_pretty(&response)? + "\n",
+            )?;
+        }
+
+        let body = fs::read_to_string(&fixture_file)?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    fn fixture_name(kind: &str, parts: &[String]) -> String {
+        let mut name = kind.to_owned();
+
+        for part in parts {
+            let part: String = part
+                .chars()
+                .map(|c| match c.is_alphanumeric() || c == '-' || c == '.' {
+                    true => c,
+                    false => '-',
+                })
+                .collect();
+
+            name.push('_');
+            name.push_str(&part);
+        }
+
+        name
+    }
+
+    pub(crate) fn locations_fixture_name(query: &str) -> String {
+        Self::fixture_name("locations", &[query.to_owned()])
+    }
+
+    pub(crate) fn nearby_fixture_name(latitude: f64, longitude: f64) -> String {
+        let parts = [
+            "near".to_owned(),
+            format!("{:.6}", latitude),
+            format!("{:.6}", longitude),
+        ];
+        Self::fixture_name("locations", &parts)
+    }
+
+    pub(crate) fn connections_fixture_name(request: &SearchConnectionRequest) -> String {
+        let mut parts = vec![request.from.clone(), request.to.clone()];
+        parts.extend(request.vias.iter().map(|via| format!("via-{}", via)));
+
+        if let Some(date) = &request.date {
+            parts.push(format!("date-{}", date));
+        }
+
+        if let Some(time) = &request.time {
+            parts.push(format!("time-{}", time));
+        }
+
+        parts.push(match request.is_arrival_time {
+            true => "arrival".to_owned(),
+            false => "departure".to_owned(),
+        });
+        parts.push(format!("page-{}", request.page));
+        parts.extend(request.transportations.iter().map(|t| t.name().to_owned()));
+
+        if request.direct {
+            parts.push("direct".to_owned());
+        }
+
+        Self::fixture_name("connections", &parts)
+    }
+
+    pub(crate) fn stationboard_fixture_name(request: &StationboardRequest) -> String {
+        let mut parts = vec![request.station.clone()];
+
+        parts.push(match request.is_arrival {
+            true => "arrival".to_owned(),
+            false => "departure".to_owned(),
+        });
+        parts.push(format!("limit-{}", request.limit));
+        parts.extend(request.transportations.iter().map(|t| t.name().to_owned()));
+
+        if let Some(datetime) = &request.datetime {
+            parts.push(format!("at-{}", datetime));
+        }
+
+        Self::fixture_name("stationboard", &parts)
+    }
+}
+
+impl TimetableBackend for FixtureBackend {
+    fn search_location(&self, query: &str) -> Result<Vec<Location>, Error> {
+        let name = Self::locations_fixture_name(query);
+        let response: LocationsResponse =
+            self.load(&name, |upstream| upstream.fetch_locations(query))?;
+
+        Ok(response.stations)
+    }
+
+    fn search_nearby(&self, latitude: f64, longitude: f64) -> Result<Vec<Location>, Error> {
+        let name = Self::nearby_fixture_name(latitude, longitude);
+        let response: LocationsResponse =
+            self.load(&name, |upstream| upstream.fetch_nearby(latitude, longitude))?;
+
+        Ok(response.into_nearby(latitude, longitude))
+    }
+
+    fn search_connection(
+        &self,
+        request: SearchConnectionRequest,
+    ) -> Result<Vec<Connection>, Error> {
+        let name = Self::connections_fixture_name(&request);
+        let response: ConnectionsResponse =
+            self.load(&name, |upstream| upstream.fetch_connections(&request))?;
+
+        response.into_connections(&request)
+    }
+
+    fn stationboard(&self, request: StationboardRequest) -> Result<Vec<StationboardEntry>, Error> {
+        let name = Self::stationboard_fixture_name(&request);
+        let response: StationboardResponse =
+            self.load(&name, |upstream| upstream.fetch_stationboard(&request))?;
+
+        Ok(response.stationboard)
+    }
+}
+
+/// The recorded connections from Zug arriving in Chur, for tests which
+/// need a realistic timetable.
+#[cfg(test)]
+pub(crate) fn zug_chur_connections() -> Vec<Connection> {
+    let request = SearchConnectionRequest {
+        from: "Zug".to_string(),
+        to: "Chur".to_string(),
+        is_arrival_time: true,
+        ..Default::default()
+    };
+
+    FixtureBackend::replay(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/api"))
+        .search_connection(request)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Capacity, LocationKind};
+
+    fn backend() -> FixtureBackend {
+        FixtureBackend::from_env(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/api"))
+    }
+
+    fn zug_chur_request() -> SearchConnectionRequest {
+        SearchConnectionRequest {
+            from: "Zug".to_string(),
+            to: "Chur".to_string(),
+            is_arrival_time: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_returns_a_non_empty_list_for_a_valid_location() {
+        let stations = backend().search_location("Basel").unwrap();
+        assert!(!stations.is_empty());
+    }
+
+    #[test]
+    fn it_tells_stations_addresses_and_points_of_interest_apart() {
+        let locations = backend().search_location("Basel Zoo").unwrap();
+        let kinds: Vec<LocationKind> = locations.iter().map(|l| l.kind()).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                LocationKind::Station,
+                LocationKind::Station,
+                LocationKind::PointOfInterest,
+                LocationKind::Address,
+            ]
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_details_of_a_location() {
+        let stations = backend().search_location("Basel").unwrap();
+
+        let station = &stations[0];
+        assert_eq!(station.id.as_deref(), Some("8500010"));
+        assert_eq!(station.kind(), LocationKind::Station);
+        assert_eq!(station.locality(), None);
+        assert_eq!(station.short_name(), "Basel SBB");
+
+        let stop = stations
+            .iter()
+            .find(|l| l.name == "Basel, Bahnhof SBB")
+            .unwrap();
+        assert_eq!(stop.locality(), Some("Basel"));
+        assert_eq!(stop.short_name(), "Bahnhof SBB");
+
+        let distance = station
+            .coordinate
+            .as_ref()
+            .and_then(|c| c.distance_to(stations[1].coordinate.as_ref()?))
+            .unwrap();
+        assert!((2_500.0..2_700.0).contains(&distance), "{}", distance);
+    }
+
+    #[test]
+    fn it_returns_the_nearest_stations_first() {
+        let stations = backend().search_nearby(47.547408, 7.589547).unwrap();
+
+        let names: Vec<&str> = stations.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names[..2], ["Basel SBB", "Basel, Bahnhof SBB"]);
+        assert!(stations.iter().all(|s| s.distance.is_some()));
+        assert!(stations.windows(2).all(|w| w[0].distance <= w[1].distance));
+    }
+
+    #[test]
+    fn it_returns_an_empty_list_for_an_empty_location() {
+        let stations = backend().search_location("").unwrap();
+        assert_eq!(stations.len(), 0);
+    }
+
+    #[test]
+    fn it_returns_an_empty_list_for_a_invalid_location() {
+        let stations = backend().search_location("ABCDEFG").unwrap();
+        assert_eq!(stations.len(), 0);
+    }
+
+    #[test]
+    fn it_returns_a_non_empty_list_for_a_valid_connection() {
+        let connections = backend().search_connection(zug_chur_request()).unwrap();
+        assert!(!connections.is_empty());
+    }
+
+    #[test]
+    fn it_deserializes_the_stops_and_sections_of_a_connection() {
+        let connections = backend().search_connection(zug_chur_request()).unwrap();
+        let connection = &connections[0];
+
+        assert_eq!(connection.from.station.name, "Zug");
+        assert_eq!(connection.to.station.name, "Chur");
+        assert!(connection.from.departure.is_some());
+        assert!(connection.to.arrival.is_some());
+
+        let first = connection.sections.first().unwrap();
+        let last = connection.sections.last().unwrap();
+        assert_eq!(first.departure.station.name, "Zug");
+        assert_eq!(last.arrival.station.name, "Chur");
+
+        let journey = first.journey.as_ref().unwrap();
+        assert!(!journey.category.is_empty());
+        assert!(journey.pass_list.len() >= 2);
+    }
+
+    #[test]
+    fn it_deserializes_prognosis_capacity_and_products() {
+        let connections = backend().search_connection(zug_chur_request()).unwrap();
+        let connection = &connections[1];
+
+        assert_eq!(connection.transfers, Some(1));
+        assert_eq!(connection.products, vec!["IR 70", "IC 3"]);
+        assert_eq!(connection.from.station.id.as_deref(), Some("8502204"));
+        assert!(connection.from.station.coordinate.is_some());
+
+        let section = &connection.sections[1];
+        let journey = section.journey.as_ref().unwrap();
+        assert_eq!(journey.operator.as_deref(), Some("SBB"));
+        assert_eq!(journey.capacity_2nd, Some(Capacity::High));
+
+        let departure = &section.departure;
+        assert_eq!(departure.delay, Some(3));
+        assert_eq!(
+            departure.expected_departure().unwrap() - departure.departure.unwrap(),
+            chrono::Duration::minutes(3)
+        );
+        assert_eq!(section.arrival.expected_platform().as_deref(), Some("9"));
+    }
+
+    #[test]
+    fn it_lists_intermediate_stops_and_transfer_times() {
+        let connections = backend().search_connection(zug_chur_request()).unwrap();
+        let sections = &connections[1].sections;
+
+        let stops: Vec<&str> = sections[0]
+            .intermediate_stops()
+            .iter()
+            .map(|s| s.station.name.as_str())
+            .collect();
+        assert_eq!(stops, vec!["Baar", "Thalwil"]);
+
+        assert_eq!(
+            sections[0].transfer_time(&sections[1]),
+            Some(chrono::Duration::minutes(10))
+        );
+    }
+
+    #[test]
+    fn it_returns_a_non_empty_stationboard_for_a_valid_station() {
+        let request = StationboardRequest {
+            station: "Bern".to_string(),
+            datetime: None,
+            is_arrival: false,
+            limit: 10,
+            transportations: vec![],
+        };
+
+        let entries = backend().stationboard(request).unwrap();
+        assert!(!entries.is_empty());
+        assert!(entries.iter().all(|e| e.stop.departure.is_some()));
+    }
+
+    #[test]
+    fn it_reports_an_unknown_station() {
+        let mut request = zug_chur_request();
+        request.from = "Xyzzy".to_string();
+        request.is_arrival_time = false;
+
+        let result = backend().search_connection(request);
+        assert!(matches!(result, Err(Error::UnknownStation(name)) if name == "Xyzzy"));
+    }
+
+    #[test]
+    fn it_reports_an_ambiguous_station_with_its_candidates() {
+        let mut request = zug_chur_request();
+        request.from = "Bahnhof".to_string();
+        request.is_arrival_time = false;
+
+        match backend().search_connection(request) {
+            Err(Error::AmbiguousStation { query, candidates }) => {
+                assert_eq!(query, "Bahnhof");
+                assert!(candidates.len() > 1);
+            }
+            other => panic!("expected an ambiguous station, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_reports_an_empty_result() {
+        let mut request = zug_chur_request();
+        request.date = Some("2030-01-01".to_string());
+        request.is_arrival_time = false;
+
+        let result = backend().search_connection(request);
+        assert!(matches!(result, Err(Error::NoConnections)));
+    }
+
+    #[test]
+    fn it_reports_a_schema_mismatch() {
+        let fixture_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/api");
+        let result = FixtureBackend::replay(fixture_dir).search_location("Broken");
+        assert!(matches!(result, Err(Error::Schema(_))));
+    }
+
+    #[test]
+    fn it_fails_if_no_fixture_was_recorded() {
+        let result = FixtureBackend::replay(env!("CARGO_MANIFEST_DIR")).search_location("Basel");
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn it_builds_file_system_safe_fixture_names() {
+        let mut request = zug_chur_request();
+        request.from = "St. Gallen, Marktplatz".to_string();
+        request.vias = vec!["Zürich HB".to_string()];
+        request.time = Some("08:00".to_string());
+
+        assert_eq!(
+            FixtureBackend::connections_fixture_name(&request),
+            "connections_St.-Gallen--Marktplatz_Chur_via-Zürich-HB_time-08-00_arrival_page-0"
+        );
+    }
+}
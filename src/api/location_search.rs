@@ -0,0 +1,424 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use crate::api::{Coordinate, Location, TimetableBackend};
+use crate::stations::{StationDirectory, StationRecord};
+
+pub const DEFAULT_WORKERS: usize = 2;
+pub const DEFAULT_CACHE_SIZE: usize = 128;
+pub const MAX_OFFLINE_RESULTS: usize = 10;
+
+type Callback = Box<dyn FnOnce(u64, Vec<Location>) + Send>;
+
+struct Job {
+    query: String,
+    sequence: u64,
+    latest: Arc<AtomicU64>,
+    callback: Callback,
+}
+
+/// Remembers the results of the most recently used queries.
+struct LruCache {
+    capacity: usize,
+    // the most recently used entry comes last
+    entries: VecDeque<(String, Vec<Location>)>,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<Vec<Location>> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(index)?;
+        let value = entry.1.clone();
+        self.entries.push_back(entry);
+
+        Some(value)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn insert(&mut self, key: String, value: Vec<Location>) {
+        self.entries.retain(|(k, _)| *k != key);
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+
+        if self.capacity > 0 {
+            self.entries.push_back((key, value));
+        }
+    }
+}
+
+/// Answers location queries on a fixed number of worker threads and caches the results.
+///
+/// Every text field uses its own `LocationSearchSession`, which drops the queries
+/// replaced by a newer one before they reach the backend. If a station directory is
+/// set, the stations starting with the query are available instantly and without
+/// network, the workers add the ones with a typo to the results of the backend.
+#[derive(Clone)]
+pub struct LocationSearch {
+    jobs: mpsc::Sender<Job>,
+    cache: Arc<Mutex<LruCache>>,
+    directory: Arc<Mutex<Option<Arc<StationDirectory>>>>,
+}
+
+impl LocationSearch {
+    pub fn new(backend: Arc<dyn TimetableBackend>) -> Self {
+        Self::with_limits(backend, DEFAULT_WORKERS, DEFAULT_CACHE_SIZE)
+    }
+
+    pub fn with_limits(
+        backend: Arc<dyn TimetableBackend>,
+        workers: usize,
+        cache_size: usize,
+    ) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let cache = Arc::new(Mutex::new(LruCache::new(cache_size)));
+        let directory = Arc::new(Mutex::new(None));
+
+        for _ in 0..workers.max(1) {
+            let receiver = receiver.clone();
+            let backend = backend.clone();
+            let cache = cache.clone();
+            let directory = directory.clone();
+
+            thread::spawn(move || loop {
+                let job = match receiver.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => return,
+                };
+
+                Self::run(&*backend, &cache, &directory, job);
+            });
+        }
+
+        Self {
+            jobs,
+            cache,
+            directory,
+        }
+    }
+
+    fn run(
+        backend: &dyn TimetableBackend,
+        cache: &Mutex<LruCache>,
+        directory: &Mutex<Option<Arc<StationDirectory>>>,
+        job: Job,
+    ) {
+        if job.latest.load(Ordering::SeqCst) != job.sequence {
+            // the text changed again while the query was waiting
+            return;
+        }
+
+        let key = Self::get_cache_key(&job.query);
+        if let Some(locations) = cache.lock().unwrap().get(&key) {
+            (job.callback)(job.sequence, locations);
+            return;
+        }
+
+        let offline = Self::search_directory(directory, &job.query, true);
+        match backend.search_location(&job.query) {
+            Ok(locations) => {
+                let locations = Self::merge(locations, offline);
+                cache.lock().unwrap().insert(key, locations.clone());
+                (job.callback)(job.sequence, locations);
+            }
+            // without network the directory still answers, but is not cached so the
+            // backend is asked again next time
+            Err(_) if !offline.is_empty() => (job.callback)(job.sequence, offline),
+            Err(_) => {}
+        }
+    }
+
+    fn get_cache_key(query: &str) -> String {
+        query.trim().to_lowercase()
+    }
+
+    pub fn get_cached(&self, query: &str) -> Option<Vec<Location>> {
+        self.cache.lock().unwrap().get(&Self::get_cache_key(query))
+    }
+
+    pub fn set_directory(&self, directory: Option<StationDirectory>) {
+        *self.directory.lock().unwrap() = directory.map(Arc::new);
+
+        // the cached results contain the matches of the previous directory
+        self.cache.lock().unwrap().clear();
+    }
+
+    pub fn has_directory(&self) -> bool {
+        self.directory.lock().unwrap().is_some()
+    }
+
+    /// The stations of the directory starting with the query, if there is one.
+    ///
+    /// This only looks up the word index and is meant to show results while typing.
+    pub fn search_offline(&self, query: &str) -> Vec<Location> {
+        Self::search_directory(&self.directory, query, false)
+    }
+
+    fn search_directory(
+        directory: &Mutex<Option<Arc<StationDirectory>>>,
+        query: &str,
+        allow_typos: bool,
+    ) -> Vec<Location> {
+        // the lock is not held while searching, which takes a while with typos
+        let directory = match &*directory.lock().unwrap() {
+            Some(directory) => directory.clone(),
+            None => return vec![],
+        };
+
+        let stations = match allow_typos {
+            true => directory.search_similar(query, MAX_OFFLINE_RESULTS),
+            false => directory.search(query, MAX_OFFLINE_RESULTS),
+        };
+
+        stations.into_iter().map(Self::to_location).collect()
+    }
+
+    fn to_location(record: &StationRecord) -> Location {
+        // only the numeric station numbers are understood by the API
+        let id = record
+            .id
+            .clone()
+            .filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()));
+
+        Location {
+            id,
+            location_type: Some("station".to_owned()),
+            name: record.name.clone(),
+            score: None,
+            coordinate: record
+                .latitude
+                .zip(record.longitude)
+                .map(|(latitude, longitude)| Coordinate::wgs84(latitude, longitude)),
+            distance: None,
+            icon: None,
+        }
+    }
+
+    /// Appends the offline matches which the network did not return.
+    fn merge(mut locations: Vec<Location>, offline: Vec<Location>) -> Vec<Location> {
+        for location in offline {
+            if !locations.iter().any(|l| l.name == location.name) {
+                locations.push(location);
+            }
+        }
+
+        locations
+    }
+
+    pub fn create_session(&self) -> LocationSearchSession {
+        LocationSearchSession {
+            search: self.clone(),
+            latest: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+/// The queries of a single text field, only the most recent one is answered.
+#[derive(Clone)]
+pub struct LocationSearchSession {
+    search: LocationSearch,
+    latest: Arc<AtomicU64>,
+}
+
+impl LocationSearchSession {
+    /// Invalidates all pending queries and returns the sequence number for the next one.
+    pub fn next_sequence(&self) -> u64 {
+        self.latest.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Whether no newer query was started since the one with this sequence number.
+    pub fn is_current(&self, sequence: u64) -> bool {
+        self.latest.load(Ordering::SeqCst) == sequence
+    }
+
+    pub fn get_cached(&self, query: &str) -> Option<Vec<Location>> {
+        self.search.get_cached(query)
+    }
+
+    pub fn search_offline(&self, query: &str) -> Vec<Location> {
+        self.search.search_offline(query)
+    }
+
+    /// Queues the query, the callback is called on a worker thread unless it is outdated.
+    pub fn search<F>(&self, query: &str, sequence: u64, callback: F)
+    where
+        F: FnOnce(u64, Vec<Location>) + Send + 'static,
+    {
+        let job = Job {
+            query: query.to_owned(),
+            sequence,
+            latest: self.latest.clone(),
+            callback: Box::new(callback),
+        };
+
+        // the workers only stop once the last sender is gone
+        let _ = self.search.jobs.send(job);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{
+        Connection, Error, LocationKind, SearchConnectionRequest, StationboardEntry,
+        StationboardRequest,
+    };
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct CountingBackend {
+        queries: Mutex<Vec<String>>,
+    }
+
+    impl TimetableBackend for CountingBackend {
+        fn search_location(&self, query: &str) -> Result<Vec<Location>, Error> {
+            self.queries.lock().unwrap().push(query.to_owned());
+            Ok(vec![location(&format!("{} Bahnhof", query))])
+        }
+
+        fn search_nearby(&self, _: f64, _: f64) -> Result<Vec<Location>, Error> {
+            Ok(vec![])
+        }
+
+        fn search_connection(&self, _: SearchConnectionRequest) -> Result<Vec<Connection>, Error> {
+            Err(Error::NoConnections)
+        }
+
+        fn stationboard(&self, _: StationboardRequest) -> Result<Vec<StationboardEntry>, Error> {
+            Ok(vec![])
+        }
+    }
+
+    fn location(name: &str) -> Location {
+        Location {
+            id: None,
+            location_type: None,
+            name: name.to_owned(),
+            score: None,
+            coordinate: None,
+            distance: None,
+            icon: None,
+        }
+    }
+
+    fn names(locations: Vec<Location>) -> Vec<String> {
+        locations.into_iter().map(|l| l.name).collect()
+    }
+
+    fn search_and_wait(session: &LocationSearchSession, query: &str) -> Option<Vec<String>> {
+        let (sender, receiver) = mpsc::channel();
+        let sequence = session.next_sequence();
+        session.search(query, sequence, move |_, locations| {
+            let _ = sender.send(names(locations));
+        });
+
+        receiver.recv_timeout(Duration::from_secs(5)).ok()
+    }
+
+    #[test]
+    fn it_answers_repeated_queries_from_the_cache() {
+        let backend = Arc::new(CountingBackend::default());
+        let session = LocationSearch::new(backend.clone()).create_session();
+
+        assert_eq!(
+            search_and_wait(&session, "Zug"),
+            Some(vec!["Zug Bahnhof".to_owned()])
+        );
+        assert_eq!(
+            search_and_wait(&session, "zug "),
+            Some(vec!["Zug Bahnhof".to_owned()])
+        );
+        assert_eq!(
+            session.get_cached("ZUG").map(names),
+            Some(vec!["Zug Bahnhof".to_owned()])
+        );
+        assert_eq!(*backend.queries.lock().unwrap(), vec!["Zug"]);
+    }
+
+    #[test]
+    fn it_skips_queries_replaced_by_a_newer_one() {
+        let backend = Arc::new(CountingBackend::default());
+        let session = LocationSearch::new(backend.clone()).create_session();
+
+        let outdated = session.next_sequence();
+        let current = session.next_sequence();
+        assert!(!session.is_current(outdated));
+        assert!(session.is_current(current));
+
+        let (sender, receiver) = mpsc::channel();
+        let outdated_sender = sender.clone();
+        session.search("Zü", outdated, move |sequence, _| {
+            let _ = outdated_sender.send(sequence);
+        });
+        session.search("Zürich", current, move |sequence, _| {
+            let _ = sender.send(sequence);
+        });
+
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(current));
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+        assert_eq!(*backend.queries.lock().unwrap(), vec!["Zürich"]);
+    }
+
+    #[test]
+    fn it_merges_the_matches_of_the_station_directory() {
+        let search = LocationSearch::new(Arc::new(CountingBackend::default()));
+        assert!(search.search_offline("Zug").is_empty());
+
+        let directory = StationDirectory::from_csv(
+            "stop_id,stop_name,stop_lat,stop_lon\n\
+             8502204,Zug,47.173618,8.515292\n\
+             8502207:0:1,Zug Metalli,47.176,8.517\n\
+             8509000,Chur,46.853,9.529\n",
+        );
+        search.set_directory(directory.ok());
+
+        let offline = search.search_offline("zug");
+        assert_eq!(offline[0].id.as_deref(), Some("8502204"));
+        assert_eq!(offline[0].kind(), LocationKind::Station);
+        assert!(offline[0].coordinate.is_some());
+        assert_eq!(names(offline), vec!["Zug", "Zug Metalli"]);
+
+        let session = search.create_session();
+        assert_eq!(
+            search_and_wait(&session, "zug"),
+            Some(vec![
+                "zug Bahnhof".to_owned(),
+                "Zug".to_owned(),
+                "Zug Metalli".to_owned()
+            ])
+        );
+
+        // typos are only looked up by the workers
+        assert!(search.search_offline("Cuhr").is_empty());
+        assert_eq!(
+            search_and_wait(&session, "Cuhr"),
+            Some(vec!["Cuhr Bahnhof".to_owned(), "Chur".to_owned()])
+        );
+    }
+
+    #[test]
+    fn it_evicts_the_least_recently_used_query() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a".to_owned(), vec![]);
+        cache.insert("b".to_owned(), vec![]);
+        cache.get("a");
+        cache.insert("c".to_owned(), vec![]);
+
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+    }
+}
@@ -1,36 +1,131 @@
+mod backend;
+mod datetime;
+mod error;
+pub(crate) mod fixture;
+mod location_search;
 mod models;
+mod opendata;
+#[cfg(any(test, feature = "stub"))]
+mod stub;
 
+pub use self::backend::TimetableBackend;
+pub use self::error::Error;
+pub use self::fixture::FixtureBackend;
+pub use self::fixture::FixtureMode;
+pub use self::location_search::LocationSearch;
+pub use self::location_search::LocationSearchSession;
+pub use self::models::Capacity;
 pub use self::models::Connection;
+pub use self::models::Coordinate;
 pub use self::models::Journey;
 pub use self::models::Location;
+pub use self::models::LocationKind;
+pub use self::models::Prognosis;
 pub use self::models::Section;
+pub use self::models::Service;
+pub use self::models::StationboardEntry;
 pub use self::models::Stop;
 pub use self::models::Walk;
+pub use self::opendata::OpendataBackend;
+pub use self::opendata::BASE_URL_ENV_VAR;
+pub use self::opendata::DEFAULT_BASE_URL;
+#[cfg(any(test, feature = "stub"))]
+pub use self::stub::StubServer;
 
-use serde::Deserialize;
+use chrono::{DateTime, Duration, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct LocationsResponse {
+    pub stations: Vec<Location>,
+}
+
+impl LocationsResponse {
+    /// Sorts by distance, which is calculated from the coordinates where missing.
+    pub(crate) fn into_nearby(self, latitude: f64, longitude: f64) -> Vec<Location> {
+        let origin = Coordinate::wgs84(latitude, longitude);
+        let mut stations = self.stations;
+
+        for station in stations.iter_mut().filter(|s| s.distance.is_none()) {
+            station.distance = station
+                .coordinate
+                .as_ref()
+                .and_then(|c| origin.distance_to(c));
+        }
+
+        stations.sort_by(|a, b| {
+            let a = a.distance.unwrap_or(f64::MAX);
+            let b = b.distance.unwrap_or(f64::MAX);
+            a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        stations
+    }
+}
 
 #[derive(Deserialize, Debug)]
-struct LocationsResponse {
-    stations: Vec<Location>,
+pub(crate) struct ConnectionsResponse {
+    pub connections: Vec<Connection>,
+
+    #[serde(default)]
+    pub stations: Option<ConnectionStations>,
 }
 
 #[derive(Deserialize, Debug)]
-struct ConnectionsResponse {
-    connections: Vec<Connection>,
+pub(crate) struct ConnectionStations {
+    #[serde(default)]
+    pub from: Option<Vec<Location>>,
+
+    #[serde(default)]
+    pub to: Option<Vec<Location>>,
 }
 
-pub fn search_location(query: &str) -> Result<Vec<String>, reqwest::Error> {
-    let url = format!(
-        "http://transport.opendata.ch/v1/locations?query={query}",
-        query = query
-    );
+impl ConnectionsResponse {
+    pub(crate) fn into_connections(
+        self,
+        request: &SearchConnectionRequest,
+    ) -> Result<Vec<Connection>, Error> {
+        if !self.connections.is_empty() {
+            return Ok(self.connections);
+        }
 
-    let response = reqwest::blocking::get(&url)?.json::<LocationsResponse>()?;
-    let locations = response.stations.into_iter().map(|s| s.name).collect();
+        if let Some(stations) = self.stations {
+            Self::check_station(&request.from, stations.from)?;
+            Self::check_station(&request.to, stations.to)?;
+        }
 
-    Ok(locations)
+        Err(Error::NoConnections)
+    }
+
+    fn check_station(query: &str, candidates: Option<Vec<Location>>) -> Result<(), Error> {
+        let candidates: Vec<String> = match candidates {
+            Some(c) => c.into_iter().map(|l| l.name).collect(),
+            None => return Ok(()),
+        };
+
+        if candidates.is_empty() {
+            return Err(Error::UnknownStation(query.to_owned()));
+        }
+
+        let is_exact_match = candidates.iter().any(|c| c.eq_ignore_ascii_case(query));
+        if candidates.len() > 1 && !is_exact_match {
+            return Err(Error::AmbiguousStation {
+                query: query.to_owned(),
+                candidates,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct StationboardResponse {
+    pub stationboard: Vec<StationboardEntry>,
 }
 
+#[derive(Debug, Clone, Default)]
 pub struct SearchConnectionRequest {
     pub from: String,
     pub to: String,
@@ -39,84 +134,160 @@ pub struct SearchConnectionRequest {
     pub time: Option<String>,
     pub is_arrival_time: bool,
     pub page: i8,
+    pub transportations: Vec<Transportation>,
+    pub direct: bool,
+    /// The ids of locations picked from a list, by their name in `from`, `to` and `vias`.
+    pub location_ids: HashMap<String, String>,
+}
+
+impl SearchConnectionRequest {
+    /// The id of the location if it is known, since names can be ambiguous.
+    pub fn query_for(&self, name: &str) -> String {
+        match self.location_ids.get(name) {
+            Some(id) => id.clone(),
+            None => name.to_owned(),
+        }
+    }
+
+    /// Builds a request for the connections departing after the given one.
+    pub fn later_than(&self, connection: &Connection) -> Option<Self> {
+        let departure = connection.from.departure?;
+        Some(self.at(departure + Duration::minutes(1), false))
+    }
+
+    /// Builds a request for the connections arriving before the given one.
+    pub fn earlier_than(&self, connection: &Connection) -> Option<Self> {
+        let arrival = connection.to.arrival?;
+        Some(self.at(arrival - Duration::minutes(1), true))
+    }
+
+    fn at(&self, time: DateTime<FixedOffset>, is_arrival_time: bool) -> Self {
+        Self {
+            date: Some(time.format("%Y-%m-%d").to_string()),
+            time: Some(time.format("%H:%M").to_string()),
+            is_arrival_time,
+            page: 0,
+            ..self.clone()
+        }
+    }
+}
+
+/// Adds the connections not yet contained in the list, ordered by departure.
+pub fn merge_connections(
+    mut connections: Vec<Connection>,
+    additional: Vec<Connection>,
+) -> Vec<Connection> {
+    for connection in additional {
+        if !connections.iter().any(|c| c.is_same_trip(&connection)) {
+            connections.push(connection);
+        }
+    }
+
+    connections.sort_by_key(|c| c.from.departure);
+    connections
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transportation {
+    Train,
+    Tram,
+    Ship,
+    Bus,
+    Cableway,
 }
 
-pub fn search_connection(
-    request: SearchConnectionRequest,
-) -> Result<Vec<Connection>, reqwest::Error> {
-    let vias = request
-        .vias
-        .iter()
-        .map(|via| format!("&via[]={}", via))
-        .collect::<Vec<String>>()
-        .join("");
-
-    let date = match request.date {
-        Some(d) => format!("&date={}", d),
-        None => String::from(""),
-    };
-
-    let time = match request.time {
-        Some(t) => format!("&time={}", t),
-        None => String::from(""),
-    };
-
-    let arrival_time = match request.is_arrival_time {
-        true => "&isArrivalTime=1",
-        false => "&isArrivalTime=0",
-    };
-
-    let url = format!(
-        "http://transport.opendata.ch/v1/connections?limit=6&from={from}&to={to}&page={page}{vias}{date}{time}{arrival_time}",
-        from = request.from,
-        to = request.to,
-        page = request.page,
-        vias = vias,
-        date = date,
-        time = time,
-        arrival_time = arrival_time,
-    );
-
-    let response = reqwest::blocking::get(&url)?.json::<ConnectionsResponse>()?;
-
-    Ok(response.connections)
+impl Transportation {
+    pub const ALL: [Transportation; 5] = [
+        Transportation::Train,
+        Transportation::Tram,
+        Transportation::Ship,
+        Transportation::Bus,
+        Transportation::Cableway,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transportation::Train => "train",
+            Transportation::Tram => "tram",
+            Transportation::Ship => "ship",
+            Transportation::Bus => "bus",
+            Transportation::Cableway => "cableway",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|t| t.name() == name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StationboardRequest {
+    pub station: String,
+    pub datetime: Option<String>,
+    pub is_arrival: bool,
+    pub limit: u8,
+    pub transportations: Vec<Transportation>,
 }
 
 #[cfg(test)]
 mod tests {
+    use super::fixture::zug_chur_connections;
     use super::*;
 
+    fn zug_chur_request() -> SearchConnectionRequest {
+        SearchConnectionRequest {
+            from: "Zug".to_string(),
+            to: "Chur".to_string(),
+            vias: vec!["Zürich HB".to_string()],
+            ..Default::default()
+        }
+    }
+
     #[test]
-    fn it_returns_a_non_empty_list_for_a_valid_location() {
-        let stations = search_location("Basel").unwrap();
-        assert!(stations.len() > 0);
+    fn it_requests_later_connections_after_the_last_departure() {
+        let connections = zug_chur_connections();
+        let request = zug_chur_request()
+            .later_than(connections.last().unwrap())
+            .unwrap();
+
+        assert_eq!(request.date.as_deref(), Some("2021-03-15"));
+        assert_eq!(request.time.as_deref(), Some("08:36"));
+        assert!(!request.is_arrival_time);
+        assert_eq!(request.vias, vec!["Zürich HB".to_string()]);
     }
 
     #[test]
-    fn it_returns_an_empty_list_for_an_empty_location() {
-        let stations = search_location("").unwrap();
-        assert_eq!(stations.len(), 0);
+    fn it_requests_earlier_connections_before_the_first_arrival() {
+        let connections = zug_chur_connections();
+        let request = zug_chur_request()
+            .earlier_than(connections.first().unwrap())
+            .unwrap();
+
+        assert_eq!(request.date.as_deref(), Some("2021-03-15"));
+        assert_eq!(request.time.as_deref(), Some("09:21"));
+        assert!(request.is_arrival_time);
     }
 
     #[test]
-    fn it_returns_an_empty_list_for_a_invalid_location() {
-        let stations = search_location("ABCDEFG").unwrap();
-        assert_eq!(stations.len(), 0);
+    fn it_merges_connections_without_duplicates() {
+        let connections = zug_chur_connections();
+        let mut additional = zug_chur_connections();
+        additional.reverse();
+
+        let merged = merge_connections(connections.clone(), additional);
+        assert_eq!(merged.len(), connections.len());
     }
 
     #[test]
-    fn it_returns_a_non_empty_list_for_a_valid_connection() {
-        let request = SearchConnectionRequest {
-            from: "Zug".to_string(),
-            to: "Chur".to_string(),
-            vias: vec![],
-            date: None,
-            time: None,
-            is_arrival_time: true,
-            page: 0,
-        };
+    fn it_orders_merged_connections_by_departure() {
+        let mut connections = zug_chur_connections();
+        let later = connections.split_off(1);
 
-        let connections = search_connection(request).unwrap();
-        assert!(connections.len() > 0);
+        let merged = merge_connections(later, connections);
+        assert_eq!(
+            merged[0].from.departure,
+            zug_chur_connections()[0].from.departure
+        );
     }
 }
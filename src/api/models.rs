@@ -1,35 +1,297 @@
-use serde::Deserialize;
+use chrono::{DateTime, Duration, FixedOffset};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Deserialize, Debug, Clone)]
+use crate::api::datetime::{
+    deserialize_duration, deserialize_optional_date_time, serialize_duration,
+    serialize_optional_date_time,
+};
+
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+// the icons of the transport modes which stop at a location
+const STATION_ICONS: &[&str] = &["train", "tram", "bus", "ship", "cableway", "funicular"];
+
+/// A position, the API uses WGS84 with the latitude in `x` and the longitude in `y`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Coordinate {
+    #[serde(rename = "type")]
+    pub coordinate_type: Option<String>,
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+}
+
+impl Coordinate {
+    pub fn wgs84(latitude: f64, longitude: f64) -> Self {
+        Self {
+            coordinate_type: Some("WGS84".to_owned()),
+            x: Some(latitude),
+            y: Some(longitude),
+        }
+    }
+
+    /// The latitude and longitude, if both are known and in WGS84.
+    pub fn latitude_longitude(&self) -> Option<(f64, f64)> {
+        match self.coordinate_type.as_deref() {
+            None | Some("WGS84") => self.x.zip(self.y),
+            Some(_) => None,
+        }
+    }
+
+    /// The great-circle distance in meters.
+    pub fn distance_to(&self, other: &Coordinate) -> Option<f64> {
+        let (lat1, lon1) = self.latitude_longitude()?;
+        let (lat2, lon2) = other.latitude_longitude()?;
+
+        let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+        let delta_lat = lat2 - lat1;
+        let delta_lon = (lon2 - lon1).to_radians();
+
+        let a = (delta_lat / 2.0).sin().powi(2)
+            + lat1.cos() * lat2.cos() * (delta_lon / 2.0).sin().powi(2);
+
+        Some(2.0 * EARTH_RADIUS_METERS * a.sqrt().asin())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocationKind {
+    Station,
+    Address,
+    PointOfInterest,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Location {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub location_type: Option<String>,
     pub name: String,
+    #[serde(default)]
+    pub score: Option<f64>,
+    #[serde(default)]
+    pub coordinate: Option<Coordinate>,
+    #[serde(default)]
+    pub distance: Option<f64>,
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+impl Location {
+    /// Uses the type where it is given, the API usually only tells the kind by the icon.
+    pub fn kind(&self) -> LocationKind {
+        match self.location_type.as_deref() {
+            Some("station") => return LocationKind::Station,
+            Some("address") => return LocationKind::Address,
+            Some("poi") => return LocationKind::PointOfInterest,
+            _ => {}
+        }
+
+        match self.icon.as_deref() {
+            Some("adr") | Some("address") => LocationKind::Address,
+            Some(icon) if STATION_ICONS.contains(&icon) => LocationKind::Station,
+            Some(_) => LocationKind::PointOfInterest,
+            // only stations have an id
+            None if self.id.is_some() => LocationKind::Station,
+            None => LocationKind::Address,
+        }
+    }
+
+    /// The place of names like `Basel, Barfüsserplatz`.
+    pub fn locality(&self) -> Option<&str> {
+        let index = self.name.find(", ")?;
+        Some(&self.name[..index])
+    }
+
+    /// The name without the locality, like `Barfüsserplatz`.
+    pub fn short_name(&self) -> &str {
+        match self.name.find(", ") {
+            Some(index) => &self.name[index + 2..],
+            None => &self.name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Capacity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Capacity {
+    fn from_level(level: u8) -> Option<Self> {
+        match level {
+            1 => Some(Capacity::Low),
+            2 => Some(Capacity::Medium),
+            3 => Some(Capacity::High),
+            _ => None,
+        }
+    }
+
+    fn level(self) -> u8 {
+        match self {
+            Capacity::Low => 1,
+            Capacity::Medium => 2,
+            Capacity::High => 3,
+        }
+    }
+}
+
+fn deserialize_capacity<'de, D>(deserializer: D) -> Result<Option<Capacity>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let level = Option::<u8>::deserialize(deserializer)?;
+    Ok(level.and_then(Capacity::from_level))
+}
+
+fn serialize_capacity<S>(capacity: &Option<Capacity>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    capacity.map(Capacity::level).serialize(serializer)
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Prognosis {
+    #[serde(default)]
+    pub platform: Option<String>,
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_date_time",
+        serialize_with = "serialize_optional_date_time"
+    )]
+    pub arrival: Option<DateTime<FixedOffset>>,
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_date_time",
+        serialize_with = "serialize_optional_date_time"
+    )]
+    pub departure: Option<DateTime<FixedOffset>>,
+
+    #[serde(
+        default,
+        rename = "capacity1st",
+        deserialize_with = "deserialize_capacity",
+        serialize_with = "serialize_capacity"
+    )]
+    pub capacity_1st: Option<Capacity>,
+
+    #[serde(
+        default,
+        rename = "capacity2nd",
+        deserialize_with = "deserialize_capacity",
+        serialize_with = "serialize_capacity"
+    )]
+    pub capacity_2nd: Option<Capacity>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Walk {
     pub duration: u16,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Journey {
+    #[serde(default)]
+    pub name: Option<String>,
     pub category: String,
+    #[serde(default)]
+    pub subcategory: Option<String>,
+    #[serde(default, rename = "categoryCode")]
+    pub category_code: Option<i32>,
     pub number: String,
+    #[serde(default)]
+    pub operator: Option<String>,
     pub to: String,
 
     #[serde(rename = "passList")]
     pub pass_list: Vec<Stop>,
+
+    #[serde(
+        default,
+        rename = "capacity1st",
+        deserialize_with = "deserialize_capacity",
+        serialize_with = "serialize_capacity"
+    )]
+    pub capacity_1st: Option<Capacity>,
+
+    #[serde(
+        default,
+        rename = "capacity2nd",
+        deserialize_with = "deserialize_capacity",
+        serialize_with = "serialize_capacity"
+    )]
+    pub capacity_2nd: Option<Capacity>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Stop {
     pub station: Location,
-    pub arrival: Option<String>,
-    pub departure: Option<String>,
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_date_time",
+        serialize_with = "serialize_optional_date_time"
+    )]
+    pub arrival: Option<DateTime<FixedOffset>>,
+
+    #[serde(default, rename = "arrivalTimestamp")]
+    pub arrival_timestamp: Option<i64>,
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_date_time",
+        serialize_with = "serialize_optional_date_time"
+    )]
+    pub departure: Option<DateTime<FixedOffset>>,
+
+    #[serde(default, rename = "departureTimestamp")]
+    pub departure_timestamp: Option<i64>,
+
     pub delay: Option<u16>,
     pub platform: Option<String>,
+
+    #[serde(default)]
+    pub prognosis: Option<Prognosis>,
+
+    #[serde(default, rename = "realtimeAvailability")]
+    pub realtime_availability: Option<String>,
+
+    #[serde(default)]
+    pub location: Option<Location>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+impl Stop {
+    /// The forecast arrival time if known, otherwise the planned one.
+    pub fn expected_arrival(&self) -> Option<DateTime<FixedOffset>> {
+        self.prognosis
+            .as_ref()
+            .and_then(|p| p.arrival)
+            .or(self.arrival)
+    }
+
+    /// The forecast departure time if known, otherwise the planned one.
+    pub fn expected_departure(&self) -> Option<DateTime<FixedOffset>> {
+        self.prognosis
+            .as_ref()
+            .and_then(|p| p.departure)
+            .or(self.departure)
+    }
+
+    /// The forecast platform if known, otherwise the planned one.
+    pub fn expected_platform(&self) -> Option<String> {
+        self.prognosis
+            .as_ref()
+            .and_then(|p| p.platform.clone())
+            .or_else(|| self.platform.clone())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Section {
     pub departure: Stop,
     pub arrival: Stop,
@@ -37,10 +299,100 @@ pub struct Section {
     pub walk: Option<Walk>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+impl Section {
+    /// The stops between departure and arrival, the pass list includes both ends.
+    pub fn intermediate_stops(&self) -> &[Stop] {
+        match self.journey.as_ref() {
+            Some(journey) if journey.pass_list.len() > 2 => {
+                &journey.pass_list[1..journey.pass_list.len() - 1]
+            }
+            _ => &[],
+        }
+    }
+
+    /// The expected time available to change from this section to the next one.
+    pub fn transfer_time(&self, next: &Section) -> Option<Duration> {
+        let arrival = self.arrival.expected_arrival()?;
+        let departure = next.departure.expected_departure()?;
+
+        Some(departure - arrival)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Service {
+    pub regular: Option<String>,
+    pub irregular: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Connection {
     pub from: Stop,
     pub to: Stop,
-    pub duration: String,
+
+    #[serde(
+        deserialize_with = "deserialize_duration",
+        serialize_with = "serialize_duration"
+    )]
+    pub duration: Duration,
+
+    #[serde(default)]
+    pub transfers: Option<u8>,
+
+    #[serde(default)]
+    pub service: Option<Service>,
+
+    #[serde(default)]
+    pub products: Vec<String>,
+
+    #[serde(
+        default,
+        rename = "capacity1st",
+        deserialize_with = "deserialize_capacity",
+        serialize_with = "serialize_capacity"
+    )]
+    pub capacity_1st: Option<Capacity>,
+
+    #[serde(
+        default,
+        rename = "capacity2nd",
+        deserialize_with = "deserialize_capacity",
+        serialize_with = "serialize_capacity"
+    )]
+    pub capacity_2nd: Option<Capacity>,
+
     pub sections: Vec<Section>,
 }
+
+impl Connection {
+    pub fn is_same_trip(&self, other: &Connection) -> bool {
+        self.from.departure == other.from.departure
+            && self.to.arrival == other.to.arrival
+            && self.journey_names() == other.journey_names()
+    }
+
+    fn journey_names(&self) -> Vec<String> {
+        self.sections
+            .iter()
+            .filter_map(|s| s.journey.as_ref())
+            .map(|j| format!("{} {}", j.category, j.number))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StationboardEntry {
+    pub stop: Stop,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub category: String,
+    #[serde(default)]
+    pub subcategory: Option<String>,
+    pub number: String,
+    #[serde(default)]
+    pub operator: Option<String>,
+    pub to: String,
+
+    #[serde(default, rename = "passList")]
+    pub pass_list: Vec<Stop>,
+}
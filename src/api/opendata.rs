@@ -0,0 +1,316 @@
+use reqwest::blocking::RequestBuilder;
+use serde::de::DeserializeOwned;
+
+use std::env;
+
+use crate::api::{
+    Connection, ConnectionsResponse, Error, Location, LocationsResponse, SearchConnectionRequest,
+    StationboardEntry, StationboardRequest, StationboardResponse, TimetableBackend,
+};
+
+pub const DEFAULT_BASE_URL: &str = "http://transport.opendata.ch/v1";
+pub const BASE_URL_ENV_VAR: &str = "SBB_API_URL";
+
+pub struct OpendataBackend {
+    base_url: String,
+    client: reqwest::blocking::Client,
+}
+
+impl OpendataBackend {
+    /// Uses the base URL from `SBB_API_URL` if set, otherwise the public service.
+    pub fn new() -> Self {
+        let base_url = Self::base_url_from_env();
+        Self::with_base_url(base_url.as_deref().unwrap_or(DEFAULT_BASE_URL))
+    }
+
+    pub fn with_base_url(base_url: &str) -> Self {
+        let client = reqwest::blocking::Client::builder()
+            .user_agent(concat!("sbb/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            client,
+        }
+    }
+
+    pub fn base_url_from_env() -> Option<String> {
+        match env::var(BASE_URL_ENV_VAR) {
+            Ok(url) if !url.is_empty() => Some(url),
+            _ => None,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub(crate) fn fetch_locations<T>(&self, query: &str) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        self.fetch(self.locations_request(query))
+    }
+
+    pub(crate) fn fetch_nearby<T>(&self, latitude: f64, longitude: f64) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        self.fetch(self.nearby_request(latitude, longitude))
+    }
+
+    pub(crate) fn fetch_connections<T>(&self, request: &SearchConnectionRequest) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        self.fetch(self.connections_request(request))
+    }
+
+    pub(crate) fn fetch_stationboard<T>(&self, request: &StationboardRequest) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        self.fetch(self.stationboard_request(request))
+    }
+
+    fn locations_request(&self, query: &str) -> RequestBuilder {
+        self.request("locations", &[("query", query.to_owned())])
+    }
+
+    fn nearby_request(&self, latitude: f64, longitude: f64) -> RequestBuilder {
+        // six decimals are precise to about 10 cm
+        let params = [
+            ("x", format!("{:.6}", latitude)),
+            ("y", format!("{:.6}", longitude)),
+            ("type", "station".to_owned()),
+        ];
+
+        self.request("locations", &params)
+    }
+
+    fn connections_request(&self, request: &SearchConnectionRequest) -> RequestBuilder {
+        let mut params = vec![
+            ("limit", "6".to_owned()),
+            ("from", request.query_for(&request.from)),
+            ("to", request.query_for(&request.to)),
+            ("page", request.page.to_string()),
+        ];
+
+        for via in &request.vias {
+            params.push(("via[]", request.query_for(via)));
+        }
+
+        if let Some(date) = &request.date {
+            params.push(("date", date.clone()));
+        }
+
+        if let Some(time) = &request.time {
+            params.push(("time", time.clone()));
+        }
+
+        let arrival_time = match request.is_arrival_time {
+            true => "1",
+            false => "0",
+        };
+        params.push(("isArrivalTime", arrival_time.to_owned()));
+
+        for transportation in &request.transportations {
+            params.push(("transportations[]", transportation.name().to_owned()));
+        }
+
+        if request.direct {
+            params.push(("direct", "1".to_owned()));
+        }
+
+        self.request("connections", &params)
+    }
+
+    fn stationboard_request(&self, request: &StationboardRequest) -> RequestBuilder {
+        let board_type = match request.is_arrival {
+            true => "arrival",
+            false => "departure",
+        };
+
+        let mut params = vec![
+            ("station", request.station.clone()),
+            ("limit", request.limit.to_string()),
+            ("type", board_type.to_owned()),
+        ];
+
+        if let Some(datetime) = &request.datetime {
+            params.push(("datetime", datetime.clone()));
+        }
+
+        for transportation in &request.transportations {
+            params.push(("transportations[]", transportation.name().to_owned()));
+        }
+
+        self.request("stationboard", &params)
+    }
+
+    fn request(&self, endpoint: &str, params: &[(&str, String)]) -> RequestBuilder {
+        let url = format!("{}/{}", self.base_url, endpoint);
+
+        self.client
+            .get(&url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .query(params)
+    }
+
+    fn fetch<T>(&self, request: RequestBuilder) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let body = request.send()?.error_for_status()?.text()?;
+        Ok(serde_json::from_str(&body)?)
+    }
+}
+
+impl Default for OpendataBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimetableBackend for OpendataBackend {
+    fn search_location(&self, query: &str) -> Result<Vec<Location>, Error> {
+        let response = self.fetch_locations::<LocationsResponse>(query)?;
+
+        Ok(response.stations)
+    }
+
+    fn search_nearby(&self, latitude: f64, longitude: f64) -> Result<Vec<Location>, Error> {
+        let response = self.fetch_nearby::<LocationsResponse>(latitude, longitude)?;
+
+        Ok(response.into_nearby(latitude, longitude))
+    }
+
+    fn search_connection(
+        &self,
+        request: SearchConnectionRequest,
+    ) -> Result<Vec<Connection>, Error> {
+        let response = self.fetch_connections::<ConnectionsResponse>(&request)?;
+
+        response.into_connections(&request)
+    }
+
+    fn stationboard(&self, request: StationboardRequest) -> Result<Vec<StationboardEntry>, Error> {
+        let response = self.fetch_stationboard::<StationboardResponse>(&request)?;
+
+        Ok(response.stationboard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Transportation;
+
+    fn backend() -> OpendataBackend {
+        OpendataBackend::with_base_url("http://localhost/v1")
+    }
+
+    fn get_query(request: RequestBuilder) -> String {
+        request.build().unwrap().url().query().unwrap().to_owned()
+    }
+
+    fn connection_request(from: &str, to: &str) -> SearchConnectionRequest {
+        SearchConnectionRequest {
+            from: from.to_string(),
+            to: to.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_encodes_umlauts_and_spaces_in_a_location_query() {
+        let query = get_query(backend().locations_request("Zürich HB"));
+        assert_eq!(query, "query=Z%C3%BCrich+HB");
+    }
+
+    #[test]
+    fn it_encodes_reserved_characters_in_a_location_query() {
+        let query = get_query(backend().locations_request("A&B+C#D"));
+        assert_eq!(query, "query=A%26B%2BC%23D");
+    }
+
+    #[test]
+    fn it_queries_stations_near_a_position() {
+        let query = get_query(backend().nearby_request(47.547408, 7.589547));
+        assert_eq!(query, "x=47.547408&y=7.589547&type=station");
+    }
+
+    #[test]
+    fn it_encodes_all_connection_parameters() {
+        let mut request = connection_request("St. Gallen, Marktplatz", "Zürich HB");
+        request.vias = vec!["Bern".to_string(), "Genève-Aéroport".to_string()];
+        request.date = Some("2021-03-15".to_string());
+        request.time = Some("08:00".to_string());
+        request.is_arrival_time = true;
+        request.page = -1;
+
+        assert_eq!(
+            get_query(backend().connections_request(&request)),
+            "limit=6&from=St.+Gallen%2C+Marktplatz&to=Z%C3%BCrich+HB&page=-1\
+             &via%5B%5D=Bern&via%5B%5D=Gen%C3%A8ve-A%C3%A9roport\
+             &date=2021-03-15&time=08%3A00&isArrivalTime=1"
+        );
+    }
+
+    #[test]
+    fn it_omits_optional_connection_parameters() {
+        let request = connection_request("Zug", "Chur");
+
+        assert_eq!(
+            get_query(backend().connections_request(&request)),
+            "limit=6&from=Zug&to=Chur&page=0&isArrivalTime=0"
+        );
+    }
+
+    #[test]
+    fn it_encodes_the_stationboard_parameters() {
+        let request = StationboardRequest {
+            station: "Bern, Bahnhof".to_string(),
+            datetime: Some("2021-03-15 08:00".to_string()),
+            is_arrival: true,
+            limit: 10,
+            transportations: vec![],
+        };
+
+        assert_eq!(
+            get_query(backend().stationboard_request(&request)),
+            "station=Bern%2C+Bahnhof&limit=10&type=arrival&datetime=2021-03-15+08%3A00"
+        );
+    }
+
+    #[test]
+    fn it_encodes_the_stationboard_transportations() {
+        let request = StationboardRequest {
+            station: "Bern".to_string(),
+            datetime: None,
+            is_arrival: false,
+            limit: 10,
+            transportations: vec![Transportation::Tram, Transportation::Bus],
+        };
+
+        assert_eq!(
+            get_query(backend().stationboard_request(&request)),
+            "station=Bern&limit=10&type=departure\
+             &transportations%5B%5D=tram&transportations%5B%5D=bus"
+        );
+    }
+
+    #[test]
+    fn it_encodes_the_connection_filters() {
+        let mut request = connection_request("Zug", "Chur");
+        request.transportations = vec![Transportation::Train, Transportation::Ship];
+        request.direct = true;
+
+        assert_eq!(
+            get_query(backend().connections_request(&request)),
+            "limit=6&from=Zug&to=Chur&page=0&isArrivalTime=0\
+             &transportations%5B%5D=train&transportations%5B%5D=ship&direct=1"
+        );
+    }
+}
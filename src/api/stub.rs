@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path;
+use std::thread;
+
+use crate::api::{FixtureBackend, SearchConnectionRequest, StationboardRequest, Transportation};
+
+/// A minimal HTTP server answering opendata requests with recorded fixtures.
+pub struct StubServer {
+    listener: TcpListener,
+    fixture_dir: path::PathBuf,
+}
+
+struct Response {
+    status: u16,
+    reason: &'static str,
+    body: String,
+}
+
+impl StubServer {
+    pub fn bind<A, P>(addr: A, fixture_dir: P) -> io::Result<Self>
+    where
+        A: ToSocketAddrs,
+        P: AsRef<path::Path>,
+    {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            fixture_dir: fixture_dir.as_ref().to_path_buf(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn base_url(&self) -> io::Result<String> {
+        Ok(format!("http://{}/v1", self.local_addr()?))
+    }
+
+    pub fn serve(&self) {
+        self.serve_with(|_, _| {});
+    }
+
+    /// Serves requests and calls `on_request` with the request line and the status of each.
+    pub fn serve_with<F>(&self, on_request: F)
+    where
+        F: Fn(&str, u16),
+    {
+        for stream in self.listener.incoming().flatten() {
+            let _ = self.handle(stream, &on_request);
+        }
+    }
+
+    pub fn spawn(self) -> thread::JoinHandle<()> {
+        thread::spawn(move || self.serve())
+    }
+
+    fn handle(&self, mut stream: TcpStream, on_request: &dyn Fn(&str, u16)) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        // skip the request headers, the stub does not need them
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+                break;
+            }
+        }
+
+        let response = self.respond(&request_line);
+        on_request(request_line.trim(), response.status);
+
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status,
+            response.reason,
+            response.body.len(),
+            response.body
+        )?;
+
+        stream.flush()
+    }
+
+    fn respond(&self, request_line: &str) -> Response {
+        let mut parts = request_line.split_whitespace();
+
+        let (method, target) = match (parts.next(), parts.next()) {
+            (Some(method), Some(target)) => (method, target),
+            _ => return Response::error(400, "Bad Request", "Malformed request line"),
+        };
+
+        if method != "GET" {
+            return Response::error(405, "Method Not Allowed", "Only GET is supported");
+        }
+
+        let url = match reqwest::Url::parse(&format!("http://stub{}", target)) {
+            Ok(url) => url,
+            Err(_) => return Response::error(400, "Bad Request", "Malformed request target"),
+        };
+
+        let params: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        let endpoint = url.path().trim_end_matches('/').rsplit('/').next();
+
+        let name = match endpoint {
+            Some("locations") => Some(Self::parse_locations(&params)),
+            Some("connections") => Self::parse_connections(&params)
+                .map(|r| FixtureBackend::connections_fixture_name(&r)),
+            Some("stationboard") => Self::parse_stationboard(&params)
+                .map(|r| FixtureBackend::stationboard_fixture_name(&r)),
+            _ => return Response::error(404, "Not Found", "Unknown endpoint"),
+        };
+
+        let name = match name {
+            Some(name) => name,
+            None => return Response::error(400, "Bad Request", "Missing required parameter"),
+        };
+
+        match fs::read_to_string(self.fixture_dir.join(format!("{}.json", name))) {
+            Ok(body) => Response {
+                status: 200,
+                reason: "OK",
+                body,
+            },
+            Err(_) => Response::error(404, "Not Found", &format!("No fixture named {}", name)),
+        }
+    }
+
+    fn get_param(params: &[(String, String)], key: &str) -> Option<String> {
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.to_owned())
+    }
+
+    fn get_transportations(params: &[(String, String)]) -> Vec<Transportation> {
+        params
+            .iter()
+            .filter(|(k, _)| k == "transportations[]")
+            .filter_map(|(_, v)| Transportation::from_name(v))
+            .collect()
+    }
+
+    fn parse_locations(params: &[(String, String)]) -> String {
+        let position = |key| Self::get_param(params, key).and_then(|v| v.parse::<f64>().ok());
+
+        match (position("x"), position("y")) {
+            (Some(latitude), Some(longitude)) => {
+                FixtureBackend::nearby_fixture_name(latitude, longitude)
+            }
+            _ => FixtureBackend::locations_fixture_name(
+                &Self::get_param(params, "query").unwrap_or_default(),
+            ),
+        }
+    }
+
+    fn parse_connections(params: &[(String, String)]) -> Option<SearchConnectionRequest> {
+        Some(SearchConnectionRequest {
+            from: Self::get_param(params, "from")?,
+            to: Self::get_param(params, "to")?,
+            vias: params
+                .iter()
+                .filter(|(k, _)| k == "via[]")
+                .map(|(_, v)| v.to_owned())
+                .collect(),
+            date: Self::get_param(params, "date"),
+            time: Self::get_param(params, "time"),
+            is_arrival_time: Self::get_param(params, "isArrivalTime").as_deref() == Some("1"),
+            page: Self::get_param(params, "page")
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(0),
+            transportations: Self::get_transportations(params),
+            direct: Self::get_param(params, "direct").as_deref() == Some("1"),
+            location_ids: HashMap::new(),
+        })
+    }
+
+    fn parse_stationboard(params: &[(String, String)]) -> Option<StationboardRequest> {
+        Some(StationboardRequest {
+            station: Self::get_param(params, "station")?,
+            datetime: Self::get_param(params, "datetime"),
+            is_arrival: Self::get_param(params, "type").as_deref() == Some("arrival"),
+            limit: Self::get_param(params, "limit")
+                .and_then(|l| l.parse().ok())
+                .unwrap_or(10),
+            transportations: Self::get_transportations(params),
+        })
+    }
+}
+
+impl Response {
+    fn error(status: u16, reason: &'static str, message: &str) -> Self {
+        let body = serde_json::json!({ "errors": [{ "message": message }] }).to_string();
+        Self {
+            status,
+            reason,
+            body,
+        }
+    }
+}
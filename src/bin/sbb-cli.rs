@@ -0,0 +1,266 @@
+use chrono::prelude::*;
+
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+use sbb::api::{
+    Connection, Location, OpendataBackend, SearchConnectionRequest, StationboardRequest,
+    TimetableBackend,
+};
+use sbb::favorites::Favorites;
+use sbb::format;
+use sbb::geo;
+use sbb::settings::Settings;
+
+const USAGE: &str = "\
+Usage:
+    sbb-cli connections FROM TO [--via STATION]... [--at TIME] [--arrive]
+                                [--format text|json|csv]
+    sbb-cli locations QUERY
+    sbb-cli nearby COORDINATES
+    sbb-cli departures STATION [--at TIME] [--arrivals] [--limit N]
+
+TIME is either HH:MM, YYYY-MM-DD or YYYY-MM-DD HH:MM. Stations may be given
+by the full name or the alias of a favorite. COORDINATES are
+WGS84 degrees like \"47.5474, 7.5895\", Swiss LV95 like \"2611363 1266768\",
+a geo: URI or a map URL.";
+
+const DEFAULT_LIMIT: u8 = 10;
+
+#[derive(Clone, Copy)]
+enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+enum Command {
+    Connections(SearchConnectionRequest, OutputFormat),
+    Locations(String),
+    Nearby(f64, f64),
+    Departures(StationboardRequest),
+}
+
+#[derive(Default)]
+struct Arguments {
+    positional: Vec<String>,
+    vias: Vec<String>,
+    at: Option<String>,
+    arrive: bool,
+    arrivals: bool,
+    limit: Option<String>,
+    format: Option<String>,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let favorites = Favorites::new();
+    favorites.connect_error(|message| eprintln!("sbb-cli: {}", message));
+
+    let command = match parse_command(&args, &favorites) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("sbb-cli: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let settings = Settings::new();
+    let backend = OpendataBackend::with_base_url(&settings.get_effective_api_base_url());
+
+    let result = match command {
+        Command::Connections(request, output) => backend
+            .search_connection(request)
+            .map(|c| print_connections(&c, output)),
+        Command::Locations(query) => backend.search_location(&query).map(|l| print_locations(&l)),
+        Command::Nearby(latitude, longitude) => backend
+            .search_nearby(latitude, longitude)
+            .map(|l| print_nearby(&l)),
+        Command::Departures(request) => {
+            let is_arrival = request.is_arrival;
+            backend
+                .stationboard(request)
+                .map(|e| write_output(&format::stationboard_to_text(&e, is_arrival)))
+        }
+    };
+
+    if let Err(error) = result {
+        eprintln!("sbb-cli: {}", error);
+        process::exit(1);
+    }
+}
+
+fn parse_command(args: &[String], favorites: &Favorites) -> Result<Command, String> {
+    let (name, args) = match args.split_first() {
+        Some((name, args)) => (name.as_str(), parse_arguments(args)?),
+        None => return Err("missing command".to_owned()),
+    };
+
+    let station = |text: &String| resolve_station(favorites, text);
+
+    match (name, args.positional.as_slice()) {
+        ("connections", [from, to]) => {
+            let (date, time) = match &args.at {
+                Some(at) => parse_at(at)?,
+                None => (None, None),
+            };
+
+            let output = match args.format.as_deref() {
+                None | Some("text") => OutputFormat::Text,
+                Some("json") => OutputFormat::Json,
+                Some("csv") => OutputFormat::Csv,
+                Some(other) => return Err(format!("unknown format \"{}\"", other)),
+            };
+
+            let request = SearchConnectionRequest {
+                from: station(from),
+                to: station(to),
+                vias: args.vias.iter().map(station).collect(),
+                date: date.map(|d| d.format("%Y-%m-%d").to_string()),
+                time: time.map(|t| t.format("%H:%M").to_string()),
+                is_arrival_time: args.arrive,
+                page: 0,
+                transportations: vec![],
+                direct: false,
+                location_ids: HashMap::new(),
+            };
+
+            Ok(Command::Connections(request, output))
+        }
+        ("locations", [query]) => Ok(Command::Locations(query.clone())),
+        ("nearby", [text]) => {
+            let (latitude, longitude) = geo::parse_coordinate(text)
+                .ok()
+                .and_then(|c| c.latitude_longitude())
+                .ok_or_else(|| format!("invalid coordinates \"{}\"", text))?;
+
+            Ok(Command::Nearby(latitude, longitude))
+        }
+        ("departures", [name]) => {
+            let datetime = match &args.at {
+                Some(at) => {
+                    let (date, time) = parse_at(at)?;
+                    let date = date.unwrap_or_else(|| Local::now().naive_local().date());
+                    let time = time.unwrap_or_else(|| NaiveTime::from_hms_opt(0, 0, 0).unwrap());
+                    Some(date.and_time(time).format("%Y-%m-%d %H:%M").to_string())
+                }
+                None => None,
+            };
+
+            let limit = match &args.limit {
+                Some(limit) => limit
+                    .parse()
+                    .map_err(|_| format!("invalid limit \"{}\"", limit))?,
+                None => DEFAULT_LIMIT,
+            };
+
+            Ok(Command::Departures(StationboardRequest {
+                station: station(name),
+                datetime,
+                is_arrival: args.arrivals,
+                limit,
+                transportations: vec![],
+            }))
+        }
+        ("connections", _) | ("locations", _) | ("nearby", _) | ("departures", _) => {
+            Err(format!("wrong number of arguments for \"{}\"", name))
+        }
+        _ => Err(format!("unknown command \"{}\"", name)),
+    }
+}
+
+fn parse_arguments(args: &[String]) -> Result<Arguments, String> {
+    let mut parsed = Arguments::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("missing value for \"{}\"", arg))
+        };
+
+        match arg.as_str() {
+            "--via" => parsed.vias.push(value()?),
+            "--at" => parsed.at = Some(value()?),
+            "--limit" => parsed.limit = Some(value()?),
+            "--format" => parsed.format = Some(value()?),
+            "--arrive" => parsed.arrive = true,
+            "--arrivals" => parsed.arrivals = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option \"{}\"", arg)),
+            _ => parsed.positional.push(arg.clone()),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_at(text: &str) -> Result<(Option<NaiveDate>, Option<NaiveTime>), String> {
+    let text = text.trim();
+
+    if let Ok(time) = NaiveTime::parse_from_str(text, "%H:%M") {
+        return Ok((None, Some(time)));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok((Some(date), None));
+    }
+
+    for format in &["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok((Some(datetime.date()), Some(datetime.time())));
+        }
+    }
+
+    Err(format!("invalid time \"{}\"", text))
+}
+
+fn resolve_station(favorites: &Favorites, text: &str) -> String {
+    match favorites.resolve(text) {
+        Some(favorite) if favorite != text => {
+            eprintln!("Using favorite \"{}\" for \"{}\"", favorite, text);
+            favorite
+        }
+        _ => text.to_owned(),
+    }
+}
+
+fn print_locations(locations: &[Location]) {
+    let text: String = locations.iter().map(|l| format!("{}\n", l.name)).collect();
+    write_output(&text);
+}
+
+fn print_nearby(locations: &[Location]) {
+    let text: String = locations
+        .iter()
+        .map(|l| {
+            let distance = l.distance.map(format::format_distance).unwrap_or_default();
+            format!("{:>8}  {}\n", distance, l.name)
+        })
+        .collect();
+    write_output(&text);
+}
+
+fn print_connections(connections: &[Connection], output: OutputFormat) {
+    match output {
+        OutputFormat::Text => write_output(&format::connections_to_text(connections)),
+        OutputFormat::Csv => write_output(&format::connections_to_csv(connections)),
+        OutputFormat::Json => match format::connections_to_json(connections) {
+            Ok(json) => write_output(&format!("{}\n", json)),
+            Err(error) => eprintln!("sbb-cli: {}", error),
+        },
+    }
+}
+
+fn write_output(text: &str) {
+    // a closed pipe (e.g. `sbb-cli ... | head`) is not an error worth reporting
+    let _ = io::stdout().write_all(text.as_bytes());
+}
@@ -0,0 +1,22 @@
+use std::env;
+
+use sbb::api::StubServer;
+
+const DEFAULT_FIXTURE_DIR: &str = "fixtures/api";
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let fixture_dir = args.get(1).map_or(DEFAULT_FIXTURE_DIR, |a| a.as_str());
+    let address = args.get(2).map_or(DEFAULT_ADDRESS, |a| a.as_str());
+
+    let server = StubServer::bind(address, fixture_dir).expect("Failed to bind stub server");
+    let base_url = server
+        .base_url()
+        .expect("Failed to read stub server address");
+
+    eprintln!("Serving fixtures from {} at {}", fixture_dir, base_url);
+    eprintln!("Start the app with SBB_API_URL={} to use it", base_url);
+
+    server.serve_with(|request, status| eprintln!("{} -> {}", request, status));
+}
@@ -0,0 +1,23 @@
+use std::fs;
+use std::path;
+
+const APP_DIR: &str = "io.chefe.sbb";
+
+/// The directory of the app below the user data directory, created if missing.
+pub fn data_dir() -> Option<path::PathBuf> {
+    create_app_dir(glib::get_user_data_dir()?)
+}
+
+/// The directory of the app below the user config directory, created if missing.
+pub fn config_dir() -> Option<path::PathBuf> {
+    create_app_dir(glib::get_user_config_dir()?)
+}
+
+fn create_app_dir(base_dir: path::PathBuf) -> Option<path::PathBuf> {
+    let dir = base_dir.join(APP_DIR);
+
+    match fs::create_dir_all(&dir) {
+        Ok(()) => Some(dir),
+        Err(_) => None,
+    }
+}
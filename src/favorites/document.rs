@@ -0,0 +1,422 @@
+use serde::{Deserialize, Serialize};
+
+/// Version 1 and 2 were keyfiles, 3 is the first JSON document.
+pub const FORMAT_VERSION: u32 = 3;
+
+/// A saved search which can be run again with a single click.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FavoriteRoute {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub vias: Vec<String>,
+    #[serde(default)]
+    pub is_arrival_time: bool,
+    /// The time of day as `HH:MM`, the current time is used if not set.
+    #[serde(default)]
+    pub time: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FavoriteStation {
+    /// The station name which is used for queries.
+    pub name: String,
+    /// A label like `Home` which is shown instead of the name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+impl FavoriteStation {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            alias: None,
+        }
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FavoritesDocument {
+    pub version: u32,
+    #[serde(default)]
+    pub stations: Vec<FavoriteStation>,
+    #[serde(default)]
+    pub routes: Vec<FavoriteRoute>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Adds new favorites and keeps the existing ones on conflicts.
+    Merge,
+    /// Discards the existing favorites.
+    Replace,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ImportReport {
+    pub stations_added: usize,
+    pub routes_added: usize,
+    /// Favorites which exist with different settings, the existing ones were kept.
+    pub conflicts: Vec<String>,
+}
+
+impl Default for FavoritesDocument {
+    fn default() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            stations: vec![],
+            routes: vec![],
+        }
+    }
+}
+
+impl FavoritesDocument {
+    pub fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+        let mut document: Self = serde_json::from_str(text)?;
+        document.normalize();
+
+        Ok(document)
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut document = self.clone();
+        document.version = FORMAT_VERSION;
+
+        // the document only contains strings and can always be serialized
+        serde_json::to_string_pretty(&document).unwrap_or_default()
+    }
+
+    /// Reads the `"; "`-joined station list of the keyfile format.
+    pub fn from_legacy_list(list: &str, routes: Vec<FavoriteRoute>) -> Self {
+        let stations = list.split("; ").map(FavoriteStation::new).collect();
+
+        let mut document = Self {
+            version: FORMAT_VERSION,
+            stations,
+            routes,
+        };
+        document.normalize();
+
+        document
+    }
+
+    /// Drops empty and duplicate entries, the first occurrence wins.
+    pub fn normalize(&mut self) {
+        for station in &mut self.stations {
+            let alias = station.alias.take();
+            station.alias = Self::clean_alias(&station.name, alias.as_deref());
+        }
+
+        let mut names: Vec<String> = vec![];
+        self.stations.retain(|s| {
+            let keep = !s.name.trim().is_empty() && !names.contains(&s.name);
+            names.push(s.name.clone());
+            keep
+        });
+
+        let mut names: Vec<String> = vec![];
+        self.routes.retain(|r| {
+            let keep = !r.name.trim().is_empty() && !names.contains(&r.name);
+            names.push(r.name.clone());
+            keep
+        });
+    }
+
+    pub fn station_names(&self) -> Vec<String> {
+        self.stations.iter().map(|s| s.name.clone()).collect()
+    }
+
+    pub fn contains_station(&self, name: &str) -> bool {
+        self.stations.iter().any(|s| s.name == name)
+    }
+
+    /// Returns false if the station is empty or already a favorite.
+    pub fn add_station(&mut self, name: &str) -> bool {
+        if name.trim().is_empty() || self.contains_station(name) {
+            return false;
+        }
+
+        self.stations.push(FavoriteStation::new(name));
+
+        true
+    }
+
+    /// Moves the station to the position of the target station.
+    pub fn move_station(&mut self, name: &str, target: &str) -> bool {
+        let from = self.stations.iter().position(|s| s.name == name);
+        let to = self.stations.iter().position(|s| s.name == target);
+
+        match (from, to) {
+            (Some(from), Some(to)) if from != to => {
+                let station = self.stations.remove(from);
+                self.stations.insert(to, station);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sets the label of the station, an empty alias shows the name again.
+    pub fn rename_station(&mut self, name: &str, alias: Option<&str>) -> bool {
+        let alias = Self::clean_alias(name, alias);
+
+        match self.stations.iter_mut().find(|s| s.name == name) {
+            Some(station) if station.alias != alias => {
+                station.alias = alias;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn clean_alias(name: &str, alias: Option<&str>) -> Option<String> {
+        let alias = alias?.trim();
+        match alias.is_empty() || alias == name {
+            true => None,
+            false => Some(alias.to_owned()),
+        }
+    }
+
+    /// Expands a station given by its name or its alias, ignoring the case.
+    ///
+    /// Prefixes are not expanded, they are often real station names themselves.
+    pub fn resolve_station(&self, text: &str) -> Option<String> {
+        let text = text.to_lowercase();
+
+        self.stations
+            .iter()
+            .find(|s| s.name.to_lowercase() == text || s.display_name().to_lowercase() == text)
+            .map(|s| s.name.clone())
+    }
+
+    pub fn remove_station(&mut self, name: &str) -> bool {
+        let count = self.stations.len();
+        self.stations.retain(|s| s.name != name);
+        count != self.stations.len()
+    }
+
+    /// Adds the route or replaces an existing route with the same name.
+    pub fn add_route(&mut self, route: FavoriteRoute) -> bool {
+        if route.name.trim().is_empty() {
+            return false;
+        }
+
+        match self.routes.iter_mut().find(|r| r.name == route.name) {
+            Some(existing) => *existing = route,
+            None => self.routes.push(route),
+        }
+
+        true
+    }
+
+    pub fn remove_route(&mut self, name: &str) -> bool {
+        let count = self.routes.len();
+        self.routes.retain(|r| r.name != name);
+        count != self.routes.len()
+    }
+}
+
+impl FavoritesDocument {
+    /// Takes over the favorites of an exported document.
+    pub fn import(&mut self, mut other: FavoritesDocument, mode: ImportMode) -> ImportReport {
+        other.normalize();
+
+        if mode == ImportMode::Replace {
+            let report = ImportReport {
+                stations_added: other.stations.len(),
+                routes_added: other.routes.len(),
+                conflicts: vec![],
+            };
+            self.stations = other.stations;
+            self.routes = other.routes;
+            return report;
+        }
+
+        let mut report = ImportReport::default();
+
+        for station in other.stations {
+            match self.stations.iter_mut().find(|s| s.name == station.name) {
+                None => {
+                    self.stations.push(station);
+                    report.stations_added += 1;
+                }
+                Some(existing) if existing.alias.is_none() => existing.alias = station.alias,
+                Some(existing) if station.alias.is_some() && existing.alias != station.alias => {
+                    report.conflicts.push(format!(
+                        "Station \"{}\" is named \"{}\" instead of \"{}\"",
+                        station.name,
+                        existing.display_name(),
+                        station.display_name()
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+
+        for route in other.routes {
+            match self.routes.iter().find(|r| r.name == route.name) {
+                None => {
+                    self.routes.push(route);
+                    report.routes_added += 1;
+                }
+                Some(existing) if *existing != route => {
+                    report.conflicts.push(format!(
+                        "Route \"{}\" differs from the existing one",
+                        route.name
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(name: &str) -> FavoriteRoute {
+        FavoriteRoute {
+            name: name.to_owned(),
+            from: "Zug".to_owned(),
+            to: "Chur".to_owned(),
+            vias: vec![],
+            is_arrival_time: false,
+            time: None,
+        }
+    }
+
+    #[test]
+    fn it_migrates_the_legacy_list_without_empty_entries() {
+        let document = FavoritesDocument::from_legacy_list("", vec![]);
+        assert!(document.stations.is_empty());
+
+        let document = FavoritesDocument::from_legacy_list("Zug; Bern; Zug", vec![]);
+        assert_eq!(document.station_names(), vec!["Zug", "Bern"]);
+    }
+
+    #[test]
+    fn it_keeps_names_containing_the_legacy_separator() {
+        let mut document = FavoritesDocument::default();
+        document.add_station("Foo; Bar");
+
+        let document = FavoritesDocument::from_json(&document.to_json()).unwrap();
+        assert_eq!(document.station_names(), vec!["Foo; Bar"]);
+    }
+
+    #[test]
+    fn it_does_not_add_duplicates() {
+        let mut document = FavoritesDocument::default();
+        assert!(document.add_station("Zug"));
+        assert!(!document.add_station("Zug"));
+        assert!(!document.add_station(" "));
+        assert_eq!(document.stations.len(), 1);
+    }
+
+    #[test]
+    fn it_moves_a_station_to_the_position_of_the_target() {
+        let mut document = FavoritesDocument::from_legacy_list("A; B; C", vec![]);
+
+        assert!(document.move_station("A", "C"));
+        assert_eq!(document.station_names(), vec!["B", "C", "A"]);
+
+        assert!(document.move_station("A", "B"));
+        assert_eq!(document.station_names(), vec!["A", "B", "C"]);
+
+        assert!(!document.move_station("A", "A"));
+        assert!(!document.move_station("X", "A"));
+    }
+
+    #[test]
+    fn it_renames_a_station_without_changing_its_name() {
+        let mut document = FavoritesDocument::default();
+        document.add_station("Bern, Wankdorf");
+
+        assert!(document.rename_station("Bern, Wankdorf", Some(" Home ")));
+        assert_eq!(document.stations[0].display_name(), "Home");
+        assert_eq!(document.station_names(), vec!["Bern, Wankdorf"]);
+
+        assert!(document.rename_station("Bern, Wankdorf", Some("")));
+        assert_eq!(document.stations[0].alias, None);
+    }
+
+    #[test]
+    fn it_resolves_names_and_aliases() {
+        let mut document = FavoritesDocument::from_legacy_list("Bern, Wankdorf; Bern; Zug", vec![]);
+        document.rename_station("Bern, Wankdorf", Some("Home"));
+
+        assert_eq!(document.resolve_station("bern"), Some("Bern".to_owned()));
+        assert_eq!(
+            document.resolve_station("home"),
+            Some("Bern, Wankdorf".to_owned())
+        );
+        assert_eq!(document.resolve_station("Zu"), None);
+        assert_eq!(document.resolve_station("Ber"), None);
+    }
+
+    #[test]
+    fn it_merges_and_reports_conflicts() {
+        let mut local = FavoritesDocument::from_legacy_list("Zug; Bern", vec![route("A")]);
+        local.rename_station("Bern", Some("Work"));
+
+        let mut other = FavoritesDocument::from_legacy_list("Bern; Chur; Zug", vec![]);
+        other.rename_station("Bern", Some("Office"));
+        other.rename_station("Zug", Some("Home"));
+        let mut changed = route("A");
+        changed.to = "Bern".to_owned();
+        other.add_route(changed);
+        other.add_route(route("B"));
+
+        let report = local.import(other, ImportMode::Merge);
+
+        assert_eq!(report.stations_added, 1);
+        assert_eq!(report.routes_added, 1);
+        assert_eq!(report.conflicts.len(), 2);
+        assert_eq!(local.station_names(), vec!["Zug", "Bern", "Chur"]);
+        assert_eq!(local.stations[0].display_name(), "Home");
+        assert_eq!(local.stations[1].display_name(), "Work");
+        assert_eq!(local.routes[0], route("A"));
+    }
+
+    #[test]
+    fn it_replaces_all_favorites() {
+        let mut local = FavoritesDocument::from_legacy_list("Zug", vec![route("A")]);
+        let other = FavoritesDocument::from_legacy_list("Chur; Chur", vec![]);
+
+        let report = local.import(other, ImportMode::Replace);
+
+        assert_eq!(report.stations_added, 1);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(local.station_names(), vec!["Chur"]);
+        assert!(local.routes.is_empty());
+    }
+
+    #[test]
+    fn it_replaces_a_route_with_the_same_name() {
+        let mut document = FavoritesDocument::default();
+        document.add_route(route("Commute"));
+
+        let mut changed = route("Commute");
+        changed.to = "Bern".to_owned();
+        document.add_route(changed);
+
+        assert_eq!(document.routes.len(), 1);
+        assert_eq!(document.routes[0].to, "Bern");
+    }
+
+    #[test]
+    fn it_reads_a_document_with_missing_optional_fields() {
+        let json = r#"{"version": 3, "routes": [{"name": "A", "from": "Zug", "to": "Chur"}]}"#;
+        let document = FavoritesDocument::from_json(json).unwrap();
+
+        assert!(document.stations.is_empty());
+        assert_eq!(document.routes, vec![route("A")]);
+    }
+}
@@ -0,0 +1,375 @@
+use gio::prelude::*;
+
+use std::fs;
+use std::path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::dirs;
+use crate::string_event_handler::StringEventHandler;
+
+mod document;
+
+pub use document::{
+    FavoriteRoute, FavoriteStation, FavoritesDocument, ImportMode, ImportReport, FORMAT_VERSION,
+};
+
+const FAVORITES_FILE: &str = "favorites.json";
+const BROKEN_FILE_SUFFIX: &str = "broken";
+
+// version 1 and 2 were stored in a keyfile, version 1 files have no version key
+const LEGACY_FILE: &str = "favorites";
+const LEGACY_GENERAL_GROUP: &str = "General";
+const LEGACY_ROUTE_GROUP_PREFIX: &str = "Route ";
+
+enum ReadError {
+    // the file may be fine, e.g. a permission problem or a file system hiccup
+    Io(String),
+    Parse(String),
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReadError::Io(e) | ReadError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Favorites {
+    data_dir: Option<path::PathBuf>,
+    document: Arc<Mutex<FavoritesDocument>>,
+    // only kept alive to receive change events
+    _monitor: Option<gio::FileMonitor>,
+    changed: gio::SimpleAction,
+    error: StringEventHandler,
+    // errors raised before a handler is connected, e.g. while loading at startup
+    pending_errors: Arc<Mutex<Option<Vec<String>>>>,
+    // set while the file could not be read, saving would overwrite its content
+    read_only: Arc<AtomicBool>,
+}
+
+impl Favorites {
+    pub fn new() -> Self {
+        let mut favorites = Self {
+            data_dir: dirs::data_dir(),
+            document: Arc::new(Mutex::new(FavoritesDocument::default())),
+            _monitor: None,
+            changed: gio::SimpleAction::new("changed", None),
+            error: StringEventHandler::new("error"),
+            pending_errors: Arc::new(Mutex::new(Some(vec![]))),
+            read_only: Arc::new(AtomicBool::new(false)),
+        };
+
+        *favorites.document.lock().unwrap() = favorites.load();
+        favorites._monitor = favorites.create_monitor();
+
+        favorites
+    }
+
+    fn get_file(&self, name: &str) -> Option<path::PathBuf> {
+        self.data_dir.as_ref().map(|dir| dir.join(name))
+    }
+
+    /// Reads the document, migrating the keyfile of older versions on first use.
+    fn load(&self) -> FavoritesDocument {
+        let favorites_file = match self.get_file(FAVORITES_FILE) {
+            Some(file) => file,
+            None => return FavoritesDocument::default(),
+        };
+
+        if !favorites_file.exists() {
+            return self.migrate_legacy_file();
+        }
+
+        match self.read_file(&favorites_file) {
+            Ok(document) => document,
+            Err(ReadError::Io(e)) => {
+                self.read_only.store(true, Ordering::SeqCst);
+                self.report_error(&format!(
+                    "The favorites could not be read, changes are not saved until they can: {}",
+                    e
+                ));
+                FavoritesDocument::default()
+            }
+            Err(ReadError::Parse(e)) => {
+                // keep the broken file so the next save does not destroy it
+                let backup = favorites_file.with_extension(format!("json.{}", BROKEN_FILE_SUFFIX));
+                let _ = fs::rename(&favorites_file, &backup);
+                self.report_error(&format!(
+                    "The favorites could not be read and were moved to {}: {}",
+                    backup.display(),
+                    e
+                ));
+                FavoritesDocument::default()
+            }
+        }
+    }
+
+    fn read_file(&self, favorites_file: &path::Path) -> Result<FavoritesDocument, ReadError> {
+        if !favorites_file.exists() {
+            return Ok(FavoritesDocument::default());
+        }
+
+        let text = fs::read_to_string(favorites_file).map_err(|e| ReadError::Io(e.to_string()))?;
+        FavoritesDocument::from_json(&text).map_err(|e| ReadError::Parse(e.to_string()))
+    }
+
+    /// Watches the file for edits of other instances or sync tools.
+    fn create_monitor(&self) -> Option<gio::FileMonitor> {
+        let favorites_file = self.get_file(FAVORITES_FILE)?;
+        let file = gio::File::new_for_path(&favorites_file);
+        let monitor = file
+            .monitor_file(gio::FileMonitorFlags::NONE, None::<&gio::Cancellable>)
+            .ok()?;
+
+        let favorites = self.clone();
+        // a deleted file is not taken over, the favorites in memory are written
+        // again with the next change
+        monitor.connect_changed(move |_, _, _, event| match event {
+            gio::FileMonitorEvent::ChangesDoneHint | gio::FileMonitorEvent::Created => {
+                favorites.reload(&favorites_file)
+            }
+            _ => {}
+        });
+
+        Some(monitor)
+    }
+
+    /// Takes over the content of the file if it differs from the one in memory.
+    fn reload(&self, favorites_file: &path::Path) {
+        // e.g. replaced in several steps by a sync tool, the next event brings the content
+        if !favorites_file.exists() {
+            return;
+        }
+
+        let document = match self.read_file(favorites_file) {
+            Ok(document) => document,
+            Err(e) => {
+                // the in-memory favorites stay valid, the next change overwrites a broken
+                // file but not one which could not be read
+                if let ReadError::Io(_) = e {
+                    self.read_only.store(true, Ordering::SeqCst);
+                }
+                self.report_error(&format!("Failed to reload favorites: {}", e));
+                return;
+            }
+        };
+
+        // the file is readable again, so saving no longer loses its content
+        self.read_only.store(false, Ordering::SeqCst);
+
+        {
+            let mut current = self.document.lock().unwrap();
+            if *current == document {
+                // this is the echo of our own write
+                return;
+            }
+
+            *current = document;
+        }
+
+        self.changed.activate(None);
+    }
+
+    fn migrate_legacy_file(&self) -> FavoritesDocument {
+        let legacy_file = match self.get_file(LEGACY_FILE) {
+            Some(file) if file.exists() => file,
+            _ => return FavoritesDocument::default(),
+        };
+
+        let key_file = glib::KeyFile::new();
+        let flags = glib::KeyFileFlags::all();
+        if let Err(e) = key_file.load_from_file(&legacy_file, flags) {
+            self.report_error(&format!("Failed to migrate favorites: {}", e));
+            return FavoritesDocument::default();
+        }
+
+        let stations = match key_file.get_string(LEGACY_GENERAL_GROUP, "Favorites") {
+            Ok(f) => f.as_str().to_owned(),
+            Err(_) => "".to_owned(),
+        };
+
+        let (groups, _) = key_file.get_groups();
+        let routes = groups
+            .iter()
+            .filter(|g| g.as_str().starts_with(LEGACY_ROUTE_GROUP_PREFIX))
+            .filter_map(|g| Self::read_legacy_route(&key_file, g.as_str()))
+            .collect();
+
+        // the old file stays untouched in case an older version is started again
+        let document = FavoritesDocument::from_legacy_list(&stations, routes);
+        if let Err(e) = self.save(&document) {
+            self.report_error(&e);
+        }
+
+        document
+    }
+
+    fn read_legacy_route(key_file: &glib::KeyFile, group: &str) -> Option<FavoriteRoute> {
+        let get = |key| key_file.get_string(group, key).ok();
+
+        let vias = match key_file.get_string_list(group, "Vias") {
+            Ok(vias) => vias.iter().map(|v| v.as_str().to_owned()).collect(),
+            Err(_) => vec![],
+        };
+
+        Some(FavoriteRoute {
+            name: get("Name")?.as_str().to_owned(),
+            from: get("From")?.as_str().to_owned(),
+            to: get("To")?.as_str().to_owned(),
+            vias,
+            is_arrival_time: key_file
+                .get_boolean(group, "IsArrivalTime")
+                .unwrap_or(false),
+            time: get("Time").map(|t| t.as_str().to_owned()),
+        })
+    }
+
+    /// Replaces the file atomically.
+    fn save(&self, document: &FavoritesDocument) -> Result<(), String> {
+        if self.read_only.load(Ordering::SeqCst) {
+            return Err(
+                "The favorites are not saved because the file could not be read".to_owned(),
+            );
+        }
+
+        let favorites_file = self
+            .get_file(FAVORITES_FILE)
+            .ok_or("Failed to store favorites: no data directory")?;
+
+        glib::file_set_contents(&favorites_file, document.to_json().as_bytes())
+            .map_err(|e| format!("Failed to store favorites: {}", e))
+    }
+
+    /// Saves the document, takes it over and notifies listeners.
+    fn commit(&self, document: FavoritesDocument) -> Result<(), String> {
+        self.save(&document)?;
+        *self.document.lock().unwrap() = document;
+        self.changed.activate(None);
+
+        Ok(())
+    }
+
+    /// Applies a change and writes it through to the file.
+    fn update<F>(&self, change: F)
+    where
+        F: FnOnce(&mut FavoritesDocument) -> bool,
+    {
+        let mut document = self.document.lock().unwrap().clone();
+        if !change(&mut document) {
+            return;
+        }
+
+        if let Err(e) = self.commit(document) {
+            self.report_error(&e);
+        }
+    }
+
+    fn report_error(&self, message: &str) {
+        if let Some(pending) = self.pending_errors.lock().unwrap().as_mut() {
+            pending.push(message.to_owned());
+            return;
+        }
+
+        self.error.trigger(message);
+    }
+
+    pub fn get(&self) -> Vec<String> {
+        self.document.lock().unwrap().station_names()
+    }
+
+    /// The favorite stations in the order chosen by the user.
+    pub fn get_stations(&self) -> Vec<FavoriteStation> {
+        self.document.lock().unwrap().stations.clone()
+    }
+
+    pub fn get_routes(&self) -> Vec<FavoriteRoute> {
+        self.document.lock().unwrap().routes.clone()
+    }
+
+    /// Adds the route or replaces an existing route with the same name.
+    pub fn add_route(&self, route: FavoriteRoute) {
+        self.update(|document| document.add_route(route));
+    }
+
+    pub fn remove_route(&self, name: &str) {
+        self.update(|document| document.remove_route(name));
+    }
+
+    pub fn add(&self, favorite: &str) {
+        self.update(|document| document.add_station(favorite));
+    }
+
+    pub fn remove(&self, favorite: &str) {
+        self.update(|document| document.remove_station(favorite));
+    }
+
+    /// Moves the favorite to the position of the target favorite.
+    pub fn move_to(&self, favorite: &str, target: &str) {
+        self.update(|document| document.move_station(favorite, target));
+    }
+
+    /// Shows the alias instead of the station name, an empty alias resets it.
+    pub fn rename(&self, favorite: &str, alias: &str) {
+        self.update(|document| document.rename_station(favorite, Some(alias)));
+    }
+
+    /// Expands a favorite given by its name or its alias.
+    pub fn resolve(&self, name: &str) -> Option<String> {
+        self.document.lock().unwrap().resolve_station(name)
+    }
+
+    /// Writes all favorites to a file which can be imported on another machine.
+    pub fn export(&self, path: &path::Path) -> Result<(), String> {
+        let json = self.document.lock().unwrap().to_json();
+        glib::file_set_contents(path, json.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    /// Reads favorites exported by `export` and merges them or replaces the existing ones.
+    pub fn import(&self, path: &path::Path, mode: ImportMode) -> Result<ImportReport, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let other = FavoritesDocument::from_json(&text).map_err(|e| e.to_string())?;
+        if other.version > FORMAT_VERSION {
+            return Err(format!(
+                "The file was exported by a newer version (format {})",
+                other.version
+            ));
+        }
+
+        let mut document = self.document.lock().unwrap().clone();
+        let report = document.import(other, mode);
+        self.commit(document)?;
+
+        Ok(report)
+    }
+
+    pub fn contains(&self, favorite: &str) -> bool {
+        self.document.lock().unwrap().contains_station(favorite)
+    }
+
+    pub fn connect_changed<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.changed.connect_activate(move |_, _| {
+            callback();
+        });
+    }
+
+    /// Called with a message whenever the favorites cannot be read or stored.
+    ///
+    /// Errors which occurred before the first callback was connected are delivered to it.
+    pub fn connect_error<F>(&self, callback: F)
+    where
+        F: Fn(&str) + 'static,
+    {
+        self.error.connect(callback);
+
+        let pending = self.pending_errors.lock().unwrap().take();
+        for message in pending.unwrap_or_default() {
+            self.error.trigger(&message);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use chrono::Duration;
+
+use std::fmt::Write;
+
+use crate::api::{Connection, Section, StationboardEntry, Stop};
+
+const CSV_HEADER: &[&str] = &[
+    "connection",
+    "journey",
+    "direction",
+    "operator",
+    "departure_station",
+    "departure_time",
+    "departure_delay",
+    "departure_platform",
+    "arrival_station",
+    "arrival_time",
+    "arrival_delay",
+    "arrival_platform",
+];
+
+/// Renders the connections as pretty printed JSON in the format of the API.
+pub fn connections_to_json(connections: &[Connection]) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(connections)
+}
+
+/// Renders the connections as CSV with one row per section.
+pub fn connections_to_csv(connections: &[Connection]) -> String {
+    let mut csv = format!("{}\r\n", CSV_HEADER.join(","));
+
+    for (index, connection) in connections.iter().enumerate() {
+        for section in &connection.sections {
+            let row = [
+                (index + 1).to_string(),
+                get_journey_name(section),
+                section
+                    .journey
+                    .as_ref()
+                    .map_or("".to_owned(), |j| j.to.clone()),
+                section
+                    .journey
+                    .as_ref()
+                    .and_then(|j| j.operator.clone())
+                    .unwrap_or_default(),
+                section.departure.station.name.clone(),
+                format_csv_time(&section.departure, false),
+                format_csv_delay(&section.departure, false),
+                format_platform(&section.departure),
+                section.arrival.station.name.clone(),
+                format_csv_time(&section.arrival, true),
+                format_csv_delay(&section.arrival, true),
+                format_platform(&section.arrival),
+            ];
+
+            let row: Vec<String> = row.iter().map(|f| escape_csv_field(f)).collect();
+            csv.push_str(&row.join(","));
+            csv.push_str("\r\n");
+        }
+    }
+
+    csv
+}
+
+/// Renders the connections as a compact table for humans.
+pub fn connections_to_text(connections: &[Connection]) -> String {
+    let mut text = String::new();
+
+    for (index, connection) in connections.iter().enumerate() {
+        if index > 0 {
+            text.push('\n');
+        }
+
+        let date = match connection.from.departure {
+            Some(departure) => departure.format("%d.%m.%Y ").to_string(),
+            None => "".to_owned(),
+        };
+
+        let _ = writeln!(
+            text,
+            "{}{} {} -> {} {}  ({}, {})",
+            date,
+            format_time(&connection.from, false),
+            connection.from.station.name,
+            format_time(&connection.to, true),
+            connection.to.station.name,
+            format_duration(&connection.duration),
+            format_transfers(connection.transfers)
+        );
+
+        for section in &connection.sections {
+            let line = format!(
+                "    {:<8} {:<11} {:<24} {:>5}  ->  {:<11} {:<24} {:>5}",
+                get_journey_name(section),
+                format_time(&section.departure, false),
+                section.departure.station.name,
+                format_platform(&section.departure),
+                format_time(&section.arrival, true),
+                section.arrival.station.name,
+                format_platform(&section.arrival),
+            );
+
+            let _ = writeln!(text, "{}", line.trim_end());
+        }
+    }
+
+    text
+}
+
+/// Renders a stationboard as a compact table for humans.
+pub fn stationboard_to_text(entries: &[StationboardEntry], is_arrival: bool) -> String {
+    let mut text = String::new();
+
+    for entry in entries {
+        let line = format!(
+            "{:<11} {:<8} {:<32} {:>5}",
+            format_time(&entry.stop, is_arrival),
+            format!("{} {}", entry.category, entry.number),
+            entry.to,
+            format_platform(&entry.stop),
+        );
+
+        let _ = writeln!(text, "{}", line.trim_end());
+    }
+
+    text
+}
+
+/// Formats a duration like `1 h 47 min`.
+pub fn format_duration(duration: &Duration) -> String {
+    let hours = duration.num_hours();
+    let minutes = duration.num_minutes() % 60;
+
+    match hours {
+        0 => format!("{} min", minutes),
+        _ => format!("{} h {} min", hours, minutes),
+    }
+}
+
+/// Formats the number of transfers like `1 transfer` or `direct`.
+pub fn format_transfers(transfers: Option<u8>) -> String {
+    match transfers {
+        Some(0) => "direct".to_owned(),
+        Some(1) => "1 transfer".to_owned(),
+        Some(n) => format!("{} transfers", n),
+        None => "transfers unknown".to_owned(),
+    }
+}
+
+/// Formats a distance like `850 m` or `12.5 km`.
+pub fn format_distance(meters: f64) -> String {
+    match meters {
+        m if m < 1000.0 => format!("{} m", (m / 10.0).round() * 10.0),
+        m => format!("{:.1} km", m / 1000.0),
+    }
+}
+
+fn get_journey_name(section: &Section) -> String {
+    match (&section.journey, &section.walk) {
+        (Some(journey), _) => format!("{} {}", journey.category, journey.number),
+        (None, Some(_)) => "Walk".to_owned(),
+        (None, None) => "".to_owned(),
+    }
+}
+
+fn get_times(stop: &Stop, is_arrival: bool) -> (Option<String>, Option<i64>) {
+    let (planned, expected) = match is_arrival {
+        true => (stop.arrival, stop.expected_arrival()),
+        false => (stop.departure, stop.expected_departure()),
+    };
+
+    let planned = match planned {
+        Some(planned) => planned,
+        None => return (None, None),
+    };
+
+    let delay = expected.map(|e| (e - planned).num_minutes());
+    (Some(planned.format("%H:%M").to_string()), delay)
+}
+
+fn format_time(stop: &Stop, is_arrival: bool) -> String {
+    match get_times(stop, is_arrival) {
+        (Some(time), Some(delay)) if delay != 0 => format!("{} ({:+})", time, delay),
+        (Some(time), _) => time,
+        (None, _) => "".to_owned(),
+    }
+}
+
+fn format_csv_time(stop: &Stop, is_arrival: bool) -> String {
+    let time = match is_arrival {
+        true => stop.arrival,
+        false => stop.departure,
+    };
+
+    time.map_or("".to_owned(), |t| t.format("%Y-%m-%d %H:%M").to_string())
+}
+
+fn format_csv_delay(stop: &Stop, is_arrival: bool) -> String {
+    match get_times(stop, is_arrival) {
+        (_, Some(delay)) => delay.to_string(),
+        _ => "".to_owned(),
+    }
+}
+
+fn format_platform(stop: &Stop) -> String {
+    match stop.expected_platform() {
+        Some(platform) => platform.trim_end_matches('!').to_owned(),
+        None => "".to_owned(),
+    }
+}
+
+fn escape_csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixture::zug_chur_connections;
+
+    #[test]
+    fn it_renders_json_which_can_be_read_again() {
+        let connections = zug_chur_connections();
+        let json = connections_to_json(&connections).unwrap();
+        let parsed: Vec<Connection> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.len(), connections.len());
+        assert!(parsed[1].is_same_trip(&connections[1]));
+        assert_eq!(parsed[1].duration, connections[1].duration);
+        assert_eq!(
+            parsed[1].sections[1].journey.as_ref().unwrap().capacity_2nd,
+            connections[1].sections[1]
+                .journey
+                .as_ref()
+                .unwrap()
+                .capacity_2nd
+        );
+    }
+
+    #[test]
+    fn it_renders_one_csv_row_per_section() {
+        let csv = connections_to_csv(&zug_chur_connections());
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], CSV_HEADER.join(","));
+        assert_eq!(
+            lines[4],
+            "2,IC 3,Chur,SBB,Zürich HB,2021-03-15 09:07,3,8,Chur,2021-03-15 10:22,3,9"
+        );
+    }
+
+    #[test]
+    fn it_quotes_csv_fields_with_separators() {
+        assert_eq!(escape_csv_field("Bern, Bahnhof"), "\"Bern, Bahnhof\"");
+        assert_eq!(escape_csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv_field("Zug"), "Zug");
+    }
+
+    #[test]
+    fn it_renders_a_text_table() {
+        let text = connections_to_text(&zug_chur_connections());
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(
+            lines[4],
+            "15.03.2021 08:35 Zug -> 10:22 (+3) Chur  (1 h 47 min, 1 transfer)"
+        );
+        assert!(lines[6].starts_with("    IC 3     09:07 (+3)  Zürich HB"));
+        assert!(lines[6].ends_with("9"));
+    }
+
+    #[test]
+    fn it_formats_distances() {
+        assert_eq!(format_distance(0.0), "0 m");
+        assert_eq!(format_distance(846.0), "850 m");
+        assert_eq!(format_distance(2_584.7), "2.6 km");
+    }
+}
@@ -0,0 +1,279 @@
+use crate::api::Coordinate;
+
+// the area of Switzerland and Liechtenstein in the Swiss LV95 grid
+const LV95_EAST: (f64, f64) = (2_480_000.0, 2_840_000.0);
+const LV95_NORTH: (f64, f64) = (1_070_000.0, 1_300_000.0);
+
+/// Reads a position typed by the user, pasted from a map URL or given as `geo:` URI.
+///
+/// Plain numbers may be WGS84 decimal degrees like `47.5474, 7.5895` or Swiss
+/// LV95 coordinates like `2'611'363 1'266'768`, which are converted to WGS84.
+pub fn parse_coordinate(text: &str) -> Result<Coordinate, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("No coordinates were given.".to_owned());
+    }
+
+    let coordinate = match text.get(..4) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("geo:") => parse_geo_uri(&text[4..]),
+        _ if text.contains("://") || text.starts_with("www.") => parse_map_url(text),
+        _ => parse_numbers(text).and_then(|(a, b)| from_numbers(a, b)),
+    };
+
+    coordinate.ok_or_else(|| format!("\"{}\" contains no known coordinates.", text))
+}
+
+/// Converts Swiss LV95 coordinates with the approximate formulas of swisstopo,
+/// which are accurate to about a meter.
+pub fn lv95_to_wgs84(east: f64, north: f64) -> (f64, f64) {
+    let y = (east - 2_600_000.0) / 1_000_000.0;
+    let x = (north - 1_200_000.0) / 1_000_000.0;
+
+    let longitude = 2.677_909_4 + 4.728_982 * y + 0.791_484 * y * x + 0.130_6 * y * x.powi(2)
+        - 0.043_6 * y.powi(3);
+    let latitude = 16.902_389_2 + 3.238_272 * x
+        - 0.270_978 * y.powi(2)
+        - 0.002_528 * x.powi(2)
+        - 0.044_7 * y.powi(2) * x
+        - 0.014_0 * x.powi(3);
+
+    // the formulas use units of 10000 seconds
+    (latitude * 100.0 / 36.0, longitude * 100.0 / 36.0)
+}
+
+fn is_lv95(east: f64, north: f64) -> bool {
+    (LV95_EAST.0..=LV95_EAST.1).contains(&east) && (LV95_NORTH.0..=LV95_NORTH.1).contains(&north)
+}
+
+fn from_wgs84(latitude: f64, longitude: f64) -> Option<Coordinate> {
+    match latitude.abs() <= 90.0 && longitude.abs() <= 180.0 {
+        true => Some(Coordinate::wgs84(latitude, longitude)),
+        false => None,
+    }
+}
+
+fn from_lv95(east: f64, north: f64) -> Option<Coordinate> {
+    match is_lv95(east, north) {
+        true => {
+            let (latitude, longitude) = lv95_to_wgs84(east, north);
+            Some(Coordinate::wgs84(latitude, longitude))
+        }
+        false => None,
+    }
+}
+
+/// Tells the systems apart by their range, LV95 may come in either order.
+fn from_numbers(a: f64, b: f64) -> Option<Coordinate> {
+    from_lv95(a, b)
+        .or_else(|| from_lv95(b, a))
+        .or_else(|| from_wgs84(a, b))
+}
+
+/// Reads two numbers, allowing thousands separators, degree signs and hemispheres.
+fn parse_numbers(text: &str) -> Option<(f64, f64)> {
+    let text = text.replace(['\'', '’'], "");
+    let mut numbers: Vec<f64> = vec![];
+
+    let tokens = text
+        .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .map(|t| t.trim_matches('°'))
+        .filter(|t| !t.is_empty());
+
+    for token in tokens {
+        let upper = token.to_uppercase();
+        let (number, hemisphere) = match upper.chars().last()? {
+            c @ 'N' | c @ 'S' | c @ 'E' | c @ 'W' => {
+                (upper[..upper.len() - 1].trim_end_matches('°'), Some(c))
+            }
+            _ => (upper.as_str(), None),
+        };
+
+        if !number.is_empty() {
+            numbers.push(number.parse().ok()?);
+        }
+
+        // a hemisphere may also follow the number as a word of its own
+        if matches!(hemisphere, Some('S') | Some('W')) {
+            let last = numbers.last_mut()?;
+            *last = -*last;
+        }
+    }
+
+    match numbers.as_slice() {
+        [a, b] => Some((*a, *b)),
+        _ => None,
+    }
+}
+
+/// Reads `geo:47.5474,7.5895;u=35` or `geo:0,0?q=47.5474,7.5895(Label)`.
+fn parse_geo_uri(text: &str) -> Option<Coordinate> {
+    let (path, query) = match text.find('?') {
+        Some(index) => (&text[..index], Some(&text[index + 1..])),
+        None => (text, None),
+    };
+
+    let query_position = query
+        .and_then(|q| q.split('&').find_map(|p| p.strip_prefix("q=")))
+        .and_then(|q| parse_pair(q.split('(').next()?));
+
+    let position = path.split(';').next().and_then(parse_pair);
+    match (position, query_position) {
+        // Android uses the origin when only a place is given
+        (Some((a, b)), Some(query)) if a == 0.0 && b == 0.0 => from_wgs84(query.0, query.1),
+        (Some((a, b)), _) => from_wgs84(a, b),
+        (None, Some(query)) => from_wgs84(query.0, query.1),
+        (None, None) => None,
+    }
+}
+
+/// Reads the position of the common map services, like `…/@47.5474,7.5895,15z`,
+/// `…#map=15/47.5474/7.5895`, `…?E=2611363&N=1266768` or `…#/map?center=2611363,1266768`.
+fn parse_map_url(text: &str) -> Option<Coordinate> {
+    let url = match text.starts_with("www.") {
+        true => reqwest::Url::parse(&format!("https://{}", text)),
+        false => reqwest::Url::parse(text),
+    }
+    .ok()?;
+
+    let mut params: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+
+    // single page apps like map.geo.admin.ch keep their parameters in the fragment
+    let fragment_query = url
+        .fragment()
+        .and_then(|f| f.split_once('?'))
+        .map(|(_, q)| q);
+    if let Some(query) = fragment_query {
+        let fragment_url = reqwest::Url::parse(&format!("http://fragment/?{}", query)).ok()?;
+        params.extend(fragment_url.query_pairs().into_owned());
+    }
+
+    let get = |keys: &[&str]| {
+        params
+            .iter()
+            .find(|(k, _)| keys.contains(&k.as_str()))
+            .and_then(|(_, v)| v.trim().parse::<f64>().ok())
+    };
+
+    if let (Some(latitude), Some(longitude)) = (get(&["mlat", "lat"]), get(&["mlon", "lon", "lng"]))
+    {
+        return from_wgs84(latitude, longitude);
+    }
+
+    if let (Some(east), Some(north)) = (get(&["E", "e"]), get(&["N", "n"])) {
+        return from_lv95(east, north);
+    }
+
+    let pair_params = ["q", "query", "ll", "center", "daddr"];
+    let pair = params
+        .iter()
+        .filter(|(k, _)| pair_params.contains(&k.as_str()))
+        .find_map(|(_, v)| parse_pair(v));
+    if let Some((a, b)) = pair {
+        return from_numbers(a, b);
+    }
+
+    let fragment = url.fragment().and_then(|f| f.strip_prefix("map="));
+    if let Some(fragment) = fragment {
+        let parts: Vec<&str> = fragment.split('/').collect();
+        if let [_, latitude, longitude, ..] = parts.as_slice() {
+            return from_wgs84(latitude.parse().ok()?, longitude.parse().ok()?);
+        }
+    }
+
+    let at = url.path().split('/').find_map(|s| s.strip_prefix('@'))?;
+    parse_pair(at).and_then(|(latitude, longitude)| from_wgs84(latitude, longitude))
+}
+
+/// Reads the first two numbers of `47.5474,7.5895` and ignores any following ones.
+fn parse_pair(text: &str) -> Option<(f64, f64)> {
+    let mut numbers = text.split(',').map(|n| n.trim().parse::<f64>());
+
+    match (numbers.next(), numbers.next()) {
+        (Some(Ok(a)), Some(Ok(b))) => Some((a, b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> (f64, f64) {
+        let coordinate = parse_coordinate(text).unwrap();
+        let (latitude, longitude) = coordinate.latitude_longitude().unwrap();
+
+        // round to about 10 meters
+        let round = |v: f64| (v * 10_000.0).round() / 10_000.0;
+        (round(latitude), round(longitude))
+    }
+
+    #[test]
+    fn it_parses_decimal_degrees() {
+        assert_eq!(parse("47.5474, 7.5895"), (47.5474, 7.5895));
+        assert_eq!(parse(" 47.5474 7.5895 "), (47.5474, 7.5895));
+        assert_eq!(parse("47.5474° N, 7.5895° E"), (47.5474, 7.5895));
+        assert_eq!(parse("33.8688S 151.2093E"), (-33.8688, 151.2093));
+    }
+
+    #[test]
+    fn it_converts_lv95_coordinates() {
+        assert_eq!(parse("2600000 1200000"), (46.9511, 7.4386));
+        assert_eq!(parse("2'611'363, 1'266'768"), (47.5515, 7.5896));
+        assert_eq!(parse("1266768 2611363"), (47.5515, 7.5896));
+    }
+
+    #[test]
+    fn it_parses_geo_uris() {
+        assert_eq!(parse("geo:47.5474,7.5895"), (47.5474, 7.5895));
+        assert_eq!(parse("GEO:47.5474,7.5895,260;u=35"), (47.5474, 7.5895));
+        assert_eq!(
+            parse("geo:0,0?q=47.5474,7.5895(Basel SBB)"),
+            (47.5474, 7.5895)
+        );
+    }
+
+    #[test]
+    fn it_parses_map_urls() {
+        assert_eq!(
+            parse("https://www.google.com/maps/@47.5474,7.5895,17z"),
+            (47.5474, 7.5895)
+        );
+        assert_eq!(
+            parse("https://www.openstreetmap.org/#map=17/47.5474/7.5895"),
+            (47.5474, 7.5895)
+        );
+        assert_eq!(
+            parse("https://www.openstreetmap.org/?mlat=47.5474&mlon=7.5895#map=17/47.5/7.5"),
+            (47.5474, 7.5895)
+        );
+        assert_eq!(
+            parse("https://map.geo.admin.ch/?lang=de&E=2611363&N=1266768&zoom=10"),
+            (47.5515, 7.5896)
+        );
+        assert_eq!(
+            parse("https://maps.google.com/?q=47.5474,7.5895"),
+            (47.5474, 7.5895)
+        );
+    }
+
+    #[test]
+    fn it_parses_the_fragment_of_current_geo_admin_links() {
+        assert_eq!(
+            parse("https://map.geo.admin.ch/#/map?lang=de&center=2611363,1266768&z=8"),
+            (47.5515, 7.5896)
+        );
+        assert_eq!(
+            parse("https://map.geo.admin.ch/#/map?lang=de&center=2611363%2C1266768&z=8&bgLayer=ch.swisstopo.pixelkarte-farbe"),
+            (47.5515, 7.5896)
+        );
+    }
+
+    #[test]
+    fn it_rejects_text_without_coordinates() {
+        assert!(parse_coordinate("").is_err());
+        assert!(parse_coordinate("Basel SBB").is_err());
+        assert!(parse_coordinate("47.5474").is_err());
+        assert!(parse_coordinate("147.5, 7.5").is_err());
+        assert!(parse_coordinate("https://www.openstreetmap.org/").is_err());
+    }
+}
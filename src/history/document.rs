@@ -0,0 +1,265 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::api::{SearchConnectionRequest, Transportation};
+
+pub const FORMAT_VERSION: u32 = 1;
+pub const MAX_ENTRIES: usize = 20;
+
+/// A past search with all of its fields except the page.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub vias: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    #[serde(default)]
+    pub is_arrival_time: bool,
+    /// Empty if all modes were selected.
+    #[serde(default)]
+    pub transportations: Vec<Transportation>,
+    #[serde(default)]
+    pub direct: bool,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub location_ids: HashMap<String, String>,
+    pub searched_at: DateTime<Utc>,
+}
+
+impl HistoryEntry {
+    pub fn from_request(request: &SearchConnectionRequest, searched_at: DateTime<Utc>) -> Self {
+        Self {
+            from: request.from.clone(),
+            to: request.to.clone(),
+            vias: request.vias.clone(),
+            date: request.date.clone(),
+            time: request.time.clone(),
+            is_arrival_time: request.is_arrival_time,
+            transportations: request.transportations.clone(),
+            direct: request.direct,
+            location_ids: request.location_ids.clone(),
+            searched_at,
+        }
+    }
+
+    /// The first page of the search.
+    pub fn to_request(&self) -> SearchConnectionRequest {
+        SearchConnectionRequest {
+            from: self.from.clone(),
+            to: self.to.clone(),
+            vias: self.vias.clone(),
+            date: self.date.clone(),
+            time: self.time.clone(),
+            is_arrival_time: self.is_arrival_time,
+            page: 0,
+            transportations: self.transportations.clone(),
+            direct: self.direct,
+            location_ids: self.location_ids.clone(),
+        }
+    }
+
+    /// Whether both entries search between the same stations.
+    pub fn is_same_route(&self, other: &HistoryEntry) -> bool {
+        self.from == other.from && self.to == other.to && self.vias == other.vias
+    }
+
+    /// The return trip through the same stations in opposite order, departing now.
+    pub fn reversed(&self) -> Self {
+        Self {
+            from: self.to.clone(),
+            to: self.from.clone(),
+            vias: self.vias.iter().rev().cloned().collect(),
+            date: None,
+            time: None,
+            is_arrival_time: false,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryDocument {
+    pub version: u32,
+    /// The most recent search comes first.
+    #[serde(default)]
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl Default for HistoryDocument {
+    fn default() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            entries: vec![],
+        }
+    }
+}
+
+impl HistoryDocument {
+    pub fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut document = self.clone();
+        document.version = FORMAT_VERSION;
+
+        serde_json::to_string_pretty(&document).unwrap_or_default()
+    }
+
+    /// Puts the search on top, an older search of the same route is dropped.
+    pub fn add(&mut self, entry: HistoryEntry) -> bool {
+        if entry.from.trim().is_empty() || entry.to.trim().is_empty() {
+            return false;
+        }
+
+        self.entries.retain(|e| !e.is_same_route(&entry));
+        self.entries.insert(0, entry);
+        self.entries.truncate(MAX_ENTRIES);
+
+        true
+    }
+
+    pub fn remove(&mut self, entry: &HistoryEntry) -> bool {
+        let count = self.entries.len();
+        self.entries.retain(|e| !e.is_same_route(entry));
+        count != self.entries.len()
+    }
+
+    pub fn clear(&mut self) -> bool {
+        let is_empty = self.entries.is_empty();
+        self.entries.clear();
+        !is_empty
+    }
+
+    /// Return trips of the most recent searches which were not searched yet.
+    pub fn reverse_suggestions(&self, limit: usize) -> Vec<HistoryEntry> {
+        let mut suggestions: Vec<HistoryEntry> = vec![];
+
+        for entry in &self.entries {
+            if suggestions.len() == limit {
+                break;
+            }
+
+            let reversed = entry.reversed();
+            let is_known = self
+                .entries
+                .iter()
+                .chain(suggestions.iter())
+                .any(|e| e.is_same_route(&reversed));
+
+            if !is_known {
+                suggestions.push(reversed);
+            }
+        }
+
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(from: &str, to: &str, vias: &[&str]) -> HistoryEntry {
+        HistoryEntry {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            vias: vias.iter().map(|v| v.to_string()).collect(),
+            date: None,
+            time: None,
+            is_arrival_time: false,
+            transportations: vec![],
+            direct: false,
+            location_ids: HashMap::new(),
+            searched_at: DateTime::parse_from_rfc3339("2021-03-15T08:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+        }
+    }
+
+    #[test]
+    fn it_moves_a_repeated_search_to_the_top() {
+        let mut document = HistoryDocument::default();
+        document.add(entry("Zug", "Chur", &[]));
+        document.add(entry("Bern", "Basel", &[]));
+        document.add(entry("Zug", "Chur", &[]));
+
+        assert_eq!(document.entries.len(), 2);
+        assert_eq!(document.entries[0].from, "Zug");
+        assert!(!document.add(entry("Zug", "", &[])));
+    }
+
+    #[test]
+    fn it_keeps_only_the_most_recent_searches() {
+        let mut document = HistoryDocument::default();
+        for index in 0..MAX_ENTRIES + 5 {
+            document.add(entry("Zug", &index.to_string(), &[]));
+        }
+
+        assert_eq!(document.entries.len(), MAX_ENTRIES);
+        assert_eq!(document.entries[0].to, (MAX_ENTRIES + 4).to_string());
+    }
+
+    #[test]
+    fn it_suggests_return_trips_not_searched_yet() {
+        let mut document = HistoryDocument::default();
+        document.add(entry("Bern", "Basel", &[]));
+        document.add(entry("Basel", "Bern", &[]));
+        document.add(entry("Zug", "Chur", &["Zürich HB", "Sargans"]));
+
+        let suggestions = document.reverse_suggestions(3);
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].from, "Chur");
+        assert_eq!(suggestions[0].vias, vec!["Sargans", "Zürich HB"]);
+    }
+
+    #[test]
+    fn it_keeps_all_fields_of_the_request_except_the_page() {
+        let mut request = entry("Zug", "Chur", &["Zürich HB"]).to_request();
+        request.date = Some("2021-03-15".to_owned());
+        request.time = Some("08:00".to_owned());
+        request.is_arrival_time = true;
+        request.page = 2;
+        request.transportations = vec![Transportation::Train, Transportation::Bus];
+        request.direct = true;
+        request
+            .location_ids
+            .insert("Zug".to_owned(), "8502204".to_owned());
+
+        let restored = HistoryEntry::from_request(&request, Utc::now()).to_request();
+
+        assert_eq!(restored.page, 0);
+        assert_eq!(restored.date, request.date);
+        assert_eq!(restored.time, request.time);
+        assert!(restored.is_arrival_time);
+        assert_eq!(restored.transportations, request.transportations);
+        assert!(restored.direct);
+        assert_eq!(restored.location_ids, request.location_ids);
+    }
+
+    #[test]
+    fn it_reads_entries_without_time_and_filters() {
+        let json = r#"{"version":1,"entries":[{"from":"Zug","to":"Chur","searched_at":"2021-03-15T08:00:00Z"}]}"#;
+        let document = HistoryDocument::from_json(json).unwrap();
+
+        assert_eq!(document.entries, vec![entry("Zug", "Chur", &[])]);
+    }
+
+    #[test]
+    fn it_survives_a_round_trip_through_json() {
+        let mut document = HistoryDocument::default();
+        let mut searched = entry("Zug", "Chur", &["Zürich HB"]);
+        searched.time = Some("08:00".to_owned());
+        searched.transportations = vec![Transportation::Cableway];
+        document.add(searched);
+
+        let parsed = HistoryDocument::from_json(&document.to_json()).unwrap();
+        assert_eq!(parsed, document);
+    }
+}
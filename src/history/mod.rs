@@ -0,0 +1,100 @@
+use gio::prelude::*;
+
+use chrono::Utc;
+
+use std::fs;
+use std::path;
+use std::sync::{Arc, Mutex};
+
+use crate::api::SearchConnectionRequest;
+use crate::dirs;
+
+mod document;
+
+pub use document::{HistoryDocument, HistoryEntry, FORMAT_VERSION, MAX_ENTRIES};
+
+const HISTORY_FILE: &str = "history.json";
+
+/// The recent searches, kept in memory and written through to a file.
+#[derive(Clone)]
+pub struct SearchHistory {
+    history_file: Option<path::PathBuf>,
+    document: Arc<Mutex<HistoryDocument>>,
+    changed: gio::SimpleAction,
+}
+
+impl SearchHistory {
+    pub fn new() -> Self {
+        let history_file = dirs::data_dir().map(|dir| dir.join(HISTORY_FILE));
+
+        // the history is a convenience, an unreadable file starts a new one
+        let document = history_file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .and_then(|text| HistoryDocument::from_json(&text).ok())
+            .unwrap_or_default();
+
+        Self {
+            history_file,
+            document: Arc::new(Mutex::new(document)),
+            changed: gio::SimpleAction::new("changed", None),
+        }
+    }
+
+    fn update<F>(&self, change: F)
+    where
+        F: FnOnce(&mut HistoryDocument) -> bool,
+    {
+        let json = {
+            let mut document = self.document.lock().unwrap();
+            if !change(&mut document) {
+                return;
+            }
+
+            document.to_json()
+        };
+
+        if let Some(file) = &self.history_file {
+            // failing to persist the history is not fatal
+            let _ = glib::file_set_contents(file, json.as_bytes());
+        }
+
+        self.changed.activate(None);
+    }
+
+    /// Records a search which found connections.
+    pub fn add(&self, request: &SearchConnectionRequest) {
+        let entry = HistoryEntry::from_request(request, Utc::now());
+        self.update(|document| document.add(entry));
+    }
+
+    pub fn remove(&self, entry: &HistoryEntry) {
+        self.update(|document| document.remove(entry));
+    }
+
+    pub fn clear(&self) {
+        self.update(|document| document.clear());
+    }
+
+    /// The most recent search comes first.
+    pub fn get_entries(&self) -> Vec<HistoryEntry> {
+        self.document.lock().unwrap().entries.clone()
+    }
+
+    pub fn get_last(&self) -> Option<HistoryEntry> {
+        self.document.lock().unwrap().entries.first().cloned()
+    }
+
+    pub fn get_reverse_suggestions(&self, limit: usize) -> Vec<HistoryEntry> {
+        self.document.lock().unwrap().reverse_suggestions(limit)
+    }
+
+    pub fn connect_changed<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.changed.connect_activate(move |_, _| {
+            callback();
+        });
+    }
+}
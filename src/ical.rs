@@ -0,0 +1,285 @@
+use chrono::{DateTime, FixedOffset, Utc};
+
+use std::fmt::Write;
+
+use crate::api::{Connection, Section, Stop};
+use crate::format;
+
+const TIMEZONE_ID: &str = "Europe/Zurich";
+const LOCAL_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+const UTC_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const MAX_LINE_LENGTH: usize = 75;
+
+// The API reports all times in swiss local time, so a single timezone suffices
+const TIMEZONE: &str = "BEGIN:VTIMEZONE
+TZID:Europe/Zurich
+BEGIN:DAYLIGHT
+TZOFFSETFROM:+0100
+TZOFFSETTO:+0200
+TZNAME:CEST
+DTSTART:19810329T020000
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU
+END:DAYLIGHT
+BEGIN:STANDARD
+TZOFFSETFROM:+0200
+TZOFFSETTO:+0100
+TZNAME:CET
+DTSTART:19961027T030000
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU
+END:STANDARD
+END:VTIMEZONE";
+
+/// Renders the connection as an iCalendar (RFC 5545) file with a single event.
+///
+/// Returns `None` if the departure or arrival time of the connection is unknown.
+pub fn connection_to_ics(connection: &Connection) -> Option<String> {
+    write_calendar(connection, Utc::now())
+}
+
+/// A file name like `Zug-Chur-2021-03-15.ics` to offer when saving the calendar.
+pub fn suggested_file_name(connection: &Connection) -> String {
+    let date = match connection.from.departure {
+        Some(departure) => departure.format("-%Y-%m-%d").to_string(),
+        None => "".to_owned(),
+    };
+
+    format!(
+        "{}-{}{}.ics",
+        sanitize(&connection.from.station.name),
+        sanitize(&connection.to.station.name),
+        date
+    )
+}
+
+fn write_calendar(connection: &Connection, now: DateTime<Utc>) -> Option<String> {
+    let departure = connection.from.departure?;
+    let arrival = connection.to.arrival?;
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        concat!("PRODID:-//chefe//sbb ", env!("CARGO_PKG_VERSION"), "//EN").to_owned(),
+        "CALSCALE:GREGORIAN".to_owned(),
+        "METHOD:PUBLISH".to_owned(),
+    ];
+    lines.extend(TIMEZONE.lines().map(|l| l.to_owned()));
+
+    lines.push("BEGIN:VEVENT".to_owned());
+    lines.push(format!("UID:{}", get_uid(connection, &departure)));
+    lines.push(format!("DTSTAMP:{}", now.format(UTC_TIME_FORMAT)));
+    lines.push(format!(
+        "DTSTART;TZID={}:{}",
+        TIMEZONE_ID,
+        departure.format(LOCAL_TIME_FORMAT)
+    ));
+    lines.push(format!(
+        "DTEND;TZID={}:{}",
+        TIMEZONE_ID,
+        arrival.format(LOCAL_TIME_FORMAT)
+    ));
+    lines.push(format!(
+        "SUMMARY:{}",
+        escape_text(&format!(
+            "{} - {}",
+            connection.from.station.name, connection.to.station.name
+        ))
+    ));
+    lines.push(format!(
+        "LOCATION:{}",
+        escape_text(&format_stop(&connection.from))
+    ));
+
+    let coordinate = connection.from.station.coordinate.as_ref();
+    if let Some((latitude, longitude)) = coordinate.and_then(|c| c.latitude_longitude()) {
+        lines.push(format!("GEO:{};{}", latitude, longitude));
+    }
+
+    lines.push(format!(
+        "DESCRIPTION:{}",
+        escape_text(&get_description(connection))
+    ));
+    lines.push("END:VEVENT".to_owned());
+    lines.push("END:VCALENDAR".to_owned());
+
+    let mut calendar = String::new();
+    for line in lines {
+        calendar.push_str(&fold_line(&line));
+        calendar.push_str("\r\n");
+    }
+
+    Some(calendar)
+}
+
+fn get_uid(connection: &Connection, departure: &DateTime<FixedOffset>) -> String {
+    let journeys: Vec<String> = connection
+        .sections
+        .iter()
+        .filter_map(|s| s.journey.as_ref())
+        .map(|j| sanitize(&format!("{}{}", j.category, j.number)))
+        .collect();
+
+    format!(
+        "{}-{}-{}-{}@sbb.chefe.io",
+        departure.with_timezone(&Utc).format(UTC_TIME_FORMAT),
+        sanitize(&connection.from.station.name),
+        sanitize(&connection.to.station.name),
+        journeys.join("-")
+    )
+}
+
+fn get_description(connection: &Connection) -> String {
+    let mut description = format!(
+        "{}, {}\n",
+        format::format_duration(&connection.duration),
+        format::format_transfers(connection.transfers)
+    );
+
+    for section in &connection.sections {
+        let _ = writeln!(
+            description,
+            "{}: {} {} - {} {}",
+            get_section_name(section),
+            format_time(&section.departure.departure),
+            format_stop(&section.departure),
+            format_time(&section.arrival.arrival),
+            format_stop(&section.arrival)
+        );
+    }
+
+    description.trim_end().to_owned()
+}
+
+fn get_section_name(section: &Section) -> String {
+    if let Some(journey) = section.journey.as_ref() {
+        return format!("{} {} to {}", journey.category, journey.number, journey.to);
+    }
+
+    if let Some(walk) = section.walk.as_ref() {
+        return format!("Walk {} min", walk.duration / 60);
+    }
+
+    "".to_owned()
+}
+
+fn format_stop(stop: &Stop) -> String {
+    match &stop.platform {
+        Some(platform) => format!(
+            "{}, Pl. {}",
+            stop.station.name,
+            platform.trim_end_matches('!')
+        ),
+        None => stop.station.name.clone(),
+    }
+}
+
+fn format_time(time: &Option<DateTime<FixedOffset>>) -> String {
+    match time {
+        Some(time) => time.format("%H:%M").to_string(),
+        None => "".to_owned(),
+    }
+}
+
+fn sanitize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits a content line into chunks of at most 75 octets without breaking characters.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+
+        folded.push(c);
+        length += c.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixture::zug_chur_connections;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2021-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn delayed_connection() -> Connection {
+        zug_chur_connections().remove(1)
+    }
+
+    #[test]
+    fn it_writes_an_event_in_swiss_local_time() {
+        let calendar = write_calendar(&delayed_connection(), now()).unwrap();
+        let lines: Vec<&str> = calendar.split("\r\n").collect();
+
+        assert_eq!(lines[0], "BEGIN:VCALENDAR");
+        assert!(lines.contains(&"TZID:Europe/Zurich"));
+        assert!(lines.contains(&"DTSTAMP:20210301T120000Z"));
+        assert!(lines.contains(&"DTSTART;TZID=Europe/Zurich:20210315T083500"));
+        assert!(lines.contains(&"DTEND;TZID=Europe/Zurich:20210315T102200"));
+        assert!(lines.contains(&"SUMMARY:Zug - Chur"));
+        assert!(lines.contains(&"LOCATION:Zug\\, Pl. 3"));
+        assert!(calendar.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn it_lists_each_section_in_the_description() {
+        let calendar = write_calendar(&delayed_connection(), now()).unwrap();
+
+        // unfold the lines again before looking at the description
+        let unfolded = calendar.replace("\r\n ", "");
+        let description = unfolded
+            .lines()
+            .find(|l| l.starts_with("DESCRIPTION:"))
+            .unwrap();
+
+        assert_eq!(
+            description,
+            "DESCRIPTION:1 h 47 min\\, 1 transfer\\n\
+             IR 70 to Zürich HB: 08:35 Zug\\, Pl. 3 - 09:00 Zürich HB\\, Pl. 7\\n\
+             IC 3 to Chur: 09:07 Zürich HB\\, Pl. 8 - 10:22 Chur\\, Pl. 9"
+        );
+    }
+
+    #[test]
+    fn it_folds_long_lines_at_character_boundaries() {
+        let line = format!("DESCRIPTION:{}", "ü".repeat(50));
+        let folded = fold_line(&line);
+
+        for part in folded.split("\r\n") {
+            assert!(part.len() <= MAX_LINE_LENGTH);
+        }
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn it_escapes_special_characters() {
+        assert_eq!(escape_text("a,b;c\\d\ne"), "a\\,b\\;c\\\\d\\ne");
+    }
+
+    #[test]
+    fn it_suggests_a_file_name() {
+        assert_eq!(
+            suggested_file_name(&delayed_connection()),
+            "Zug-Chur-2021-03-15.ics"
+        );
+    }
+}
@@ -1,4 +1,11 @@
 pub mod api;
+pub mod dirs;
 pub mod favorites;
+pub mod format;
+pub mod geo;
+pub mod history;
+pub mod ical;
+pub mod settings;
+pub mod stations;
 pub mod string_event_handler;
 pub mod widgets;
@@ -2,18 +2,45 @@ use gio::prelude::*;
 use gtk::prelude::*;
 use libhandy::prelude::*;
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::thread;
 
+use sbb::api::{
+    Connection, Error, LocationSearch, OpendataBackend, SearchConnectionRequest, TimetableBackend,
+};
 use sbb::favorites::Favorites;
+use sbb::history::SearchHistory;
+use sbb::settings::Settings;
 use sbb::widgets::*;
 
 const APP_TITLE: &str = "SBB";
 const MAIN_PAGE: &str = "main_page";
 const CONNECTION_LIST_PAGE: &str = "connection_list_page";
+const CONNECTION_DETAIL_PAGE: &str = "connection_detail_page";
+const STATIONBOARD_PAGE: &str = "stationboard_page";
 const PAGE_WIDTH: i32 = 320;
 const PAGE_HEIGHT: i32 = -1;
 const WINDOW_WIDTH: i32 = 360;
 const WINDOW_HEIGHT: i32 = 648;
+const RESPONSE_RETRY: u16 = 1000;
+const RESPONSE_EDIT: u16 = 1001;
+const MAX_STATION_CANDIDATES: usize = 5;
+
+#[derive(Clone, Copy)]
+enum SearchKind {
+    New,
+    Earlier,
+    Later,
+}
+
+enum SearchMessage {
+    Finished(
+        usize,
+        SearchKind,
+        SearchConnectionRequest,
+        Result<Vec<Connection>, Error>,
+    ),
+}
 
 fn main() {
     let app = gtk::Application::new(Some("io.chefe.sbb"), Default::default())
@@ -43,12 +70,43 @@ fn build_ui(app: &gtk::Application) {
         .build();
     connection_list_header.add(&back_button);
 
+    let detail_back_button =
+        gtk::Button::from_icon_name(Some("go-previous-symbolic"), gtk::IconSize::Menu);
+
+    let connection_detail_header = libhandy::HeaderBarBuilder::new()
+        .title("Connection")
+        .show_close_button(true)
+        .hexpand(true)
+        .build();
+    connection_detail_header.add(&detail_back_button);
+
+    let stationboard_button =
+        gtk::Button::from_icon_name(Some("view-list-symbolic"), gtk::IconSize::Menu);
+    stationboard_button.set_tooltip_text(Some("Departures"));
+    main_header.pack_end(&stationboard_button);
+
+    let stationboard_back_button =
+        gtk::Button::from_icon_name(Some("go-previous-symbolic"), gtk::IconSize::Menu);
+
+    let stationboard_header = libhandy::HeaderBarBuilder::new()
+        .title("Departures")
+        .show_close_button(true)
+        .hexpand(true)
+        .build();
+    stationboard_header.add(&stationboard_back_button);
+
     let header_group = libhandy::HeaderGroup::new();
     header_group.add_header_bar(&main_header);
     header_group.add_header_bar(&connection_list_header);
+    header_group.add_header_bar(&connection_detail_header);
+    header_group.add_header_bar(&stationboard_header);
 
     let title_separator = gtk::Separator::new(gtk::Orientation::Vertical);
     let content_separator = gtk::Separator::new(gtk::Orientation::Vertical);
+    let detail_title_separator = gtk::Separator::new(gtk::Orientation::Vertical);
+    let detail_content_separator = gtk::Separator::new(gtk::Orientation::Vertical);
+    let stationboard_title_separator = gtk::Separator::new(gtk::Orientation::Vertical);
+    let stationboard_content_separator = gtk::Separator::new(gtk::Orientation::Vertical);
 
     let title_leaflet = libhandy::Leaflet::new();
     title_leaflet.add(&main_header);
@@ -56,6 +114,12 @@ fn build_ui(app: &gtk::Application) {
     title_leaflet.add(&title_separator);
     title_leaflet.add(&connection_list_header);
     title_leaflet.set_child_name(&connection_list_header, Some(CONNECTION_LIST_PAGE));
+    title_leaflet.add(&detail_title_separator);
+    title_leaflet.add(&connection_detail_header);
+    title_leaflet.set_child_name(&connection_detail_header, Some(CONNECTION_DETAIL_PAGE));
+    title_leaflet.add(&stationboard_title_separator);
+    title_leaflet.add(&stationboard_header);
+    title_leaflet.set_child_name(&stationboard_header, Some(STATIONBOARD_PAGE));
 
     let title_bar = libhandy::TitleBar::new();
     title_bar.add(&title_leaflet);
@@ -66,23 +130,88 @@ fn build_ui(app: &gtk::Application) {
     window.set_titlebar(Some(&title_bar));
 
     let favorites = Arc::new(Favorites::new());
+    let error_window = window.clone();
+    favorites.connect_error(move |message| {
+        show_favorites_error(message, &error_window);
+    });
 
-    let search_page = SearchWidget::new(favorites.clone());
+    let history = Arc::new(SearchHistory::new());
+    let settings = Arc::new(Settings::new());
+    let backend = create_backend(&settings);
+    let location_search = LocationSearch::new(backend.clone());
+
+    let main_menu = MainMenuWidget::new(favorites.clone(), location_search.clone());
+    main_header.pack_start(&main_menu.container);
+
+    let search_page = SearchWidget::new(
+        favorites.clone(),
+        history.clone(),
+        location_search.clone(),
+        settings.clone(),
+    );
     search_page
         .container
         .set_size_request(PAGE_WIDTH, PAGE_HEIGHT);
 
+    let parent = window.clone();
+    let nearby_backend = backend.clone();
+    let page = search_page.clone();
+    main_menu.connect_nearby_stations(move || {
+        let nearby = NearbyStationsWidget::new(&parent, nearby_backend.clone());
+
+        let from_page = page.clone();
+        nearby.connect_use_as_from(move |station| {
+            from_page.set_from_location(station);
+        });
+
+        let to_page = page.clone();
+        nearby.connect_use_as_to(move |station| {
+            to_page.set_to_location(station);
+        });
+
+        nearby.show();
+    });
+
     let connection_list_page = ConnectionListWidget::new();
     connection_list_page
         .container
         .set_size_request(PAGE_WIDTH, PAGE_HEIGHT);
 
+    let connection_detail_page = ConnectionDetailWidget::new();
+    connection_detail_page
+        .container
+        .set_size_request(PAGE_WIDTH, PAGE_HEIGHT);
+
+    let stationboard_page =
+        StationboardWidget::new(favorites.clone(), backend.clone(), location_search);
+    stationboard_page
+        .container
+        .set_size_request(PAGE_WIDTH, PAGE_HEIGHT);
+
+    let header = stationboard_header.clone();
+    stationboard_page.connect_arrival_changed(move |is_arrival| {
+        let title = match is_arrival {
+            true => "Arrivals",
+            false => "Departures",
+        };
+        header.set_title(Some(title));
+    });
+
     let content_leaflet = libhandy::Leaflet::new();
     content_leaflet.add(&search_page.container);
     content_leaflet.set_child_name(&search_page.container, Some(MAIN_PAGE));
     content_leaflet.add(&content_separator);
     content_leaflet.add(&connection_list_page.container);
     content_leaflet.set_child_name(&connection_list_page.container, Some(CONNECTION_LIST_PAGE));
+    content_leaflet.add(&detail_content_separator);
+    content_leaflet.add(&connection_detail_page.container);
+    content_leaflet.set_child_name(
+        &connection_detail_page.container,
+        Some(CONNECTION_DETAIL_PAGE),
+    );
+    content_leaflet.add(&stationboard_content_separator);
+    content_leaflet.add(&stationboard_page.container);
+    content_leaflet.set_child_name(&stationboard_page.container, Some(STATIONBOARD_PAGE));
 
     let left_page_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Horizontal);
     left_page_size_group.add_widget(&main_header);
@@ -92,10 +221,26 @@ fn build_ui(app: &gtk::Application) {
     right_page_size_group.add_widget(&connection_list_header);
     right_page_size_group.add_widget(&connection_list_page.container);
 
+    let detail_page_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Horizontal);
+    detail_page_size_group.add_widget(&connection_detail_header);
+    detail_page_size_group.add_widget(&connection_detail_page.container);
+
+    let stationboard_page_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Horizontal);
+    stationboard_page_size_group.add_widget(&stationboard_header);
+    stationboard_page_size_group.add_widget(&stationboard_page.container);
+
     let separator_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Horizontal);
     separator_size_group.add_widget(&title_separator);
     separator_size_group.add_widget(&content_separator);
 
+    let detail_separator_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Horizontal);
+    detail_separator_size_group.add_widget(&detail_title_separator);
+    detail_separator_size_group.add_widget(&detail_content_separator);
+
+    let stationboard_separator_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Horizontal);
+    stationboard_separator_size_group.add_widget(&stationboard_title_separator);
+    stationboard_separator_size_group.add_widget(&stationboard_content_separator);
+
     content_leaflet
         .bind_property("visible-child-name", &title_leaflet, "visible-child-name")
         .flags(glib::BindingFlags::BIDIRECTIONAL | glib::BindingFlags::SYNC_CREATE)
@@ -106,29 +251,155 @@ fn build_ui(app: &gtk::Application) {
         .flags(glib::BindingFlags::SYNC_CREATE)
         .build();
 
+    content_leaflet
+        .bind_property("folded", &detail_back_button, "visible")
+        .flags(glib::BindingFlags::SYNC_CREATE)
+        .build();
+
+    content_leaflet
+        .bind_property("folded", &stationboard_back_button, "visible")
+        .flags(glib::BindingFlags::SYNC_CREATE)
+        .build();
+
+    // every search gets a new id, results of an older search are dropped
+    let current_search = Arc::new(Mutex::new(0));
+    let last_request: Arc<Mutex<Option<SearchConnectionRequest>>> = Arc::new(Mutex::new(None));
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
     let leaflet = content_leaflet.clone();
-    search_page.connect_search(move |data| match sbb::api::search_connection(data) {
-        Ok(connections) => {
-            connection_list_page.set_connections(connections);
-            leaflet.set_visible_child_name(CONNECTION_LIST_PAGE);
+    let page = search_page.clone();
+    let list = connection_list_page.clone();
+    let search_id = current_search.clone();
+    let request = last_request.clone();
+    let search_backend = backend.clone();
+    let search_sender = sender.clone();
+    search_page.connect_search(move |data| {
+        page.set_searching(true);
+        list.set_loading(true);
+        leaflet.set_visible_child_name(CONNECTION_LIST_PAGE);
+
+        *request.lock().unwrap() = Some(data.clone());
+        spawn_search(
+            &search_backend,
+            &search_sender,
+            &search_id,
+            SearchKind::New,
+            data,
+        );
+    });
+
+    let list = connection_list_page.clone();
+    let search_id = current_search.clone();
+    let request = last_request.clone();
+    let search_backend = backend.clone();
+    let search_sender = sender.clone();
+    connection_list_page.connect_load_earlier(move || {
+        let data = match (&*request.lock().unwrap(), list.get_first_connection()) {
+            (Some(r), Some(c)) => r.earlier_than(&c),
+            _ => None,
+        };
+
+        if let Some(data) = data {
+            list.set_paging(true);
+            spawn_search(
+                &search_backend,
+                &search_sender,
+                &search_id,
+                SearchKind::Earlier,
+                data,
+            );
+        }
+    });
+
+    let list = connection_list_page.clone();
+    let search_id = current_search.clone();
+    let request = last_request.clone();
+    let search_backend = backend.clone();
+    let search_sender = sender.clone();
+    connection_list_page.connect_load_later(move || {
+        let data = match (&*request.lock().unwrap(), list.get_last_connection()) {
+            (Some(r), Some(c)) => r.later_than(&c),
+            _ => None,
+        };
+
+        if let Some(data) = data {
+            list.set_paging(true);
+            spawn_search(
+                &search_backend,
+                &search_sender,
+                &search_id,
+                SearchKind::Later,
+                data,
+            );
         }
-        Err(_) => {
-            connection_list_page.set_connections(vec![]);
-
-            let dialog = gtk::MessageDialogBuilder::new()
-                .modal(true)
-                .message_type(gtk::MessageType::Error)
-                .title("Error")
-                .text("Search failed! Please verify\nthat you are connected to\nthe internet and then retry.")
-                .buttons(gtk::ButtonsType::Ok)
-                .build();
-
-            dialog.connect_response(|d, _| unsafe {
-                d.destroy();
-            });
-
-            dialog.show_all();
+    });
+
+    let leaflet = content_leaflet.clone();
+    let page = search_page.clone();
+    let list = connection_list_page.clone();
+    let search_id = current_search.clone();
+    connection_list_page.connect_cancel(move || {
+        *search_id.lock().unwrap() += 1;
+
+        page.set_searching(false);
+        list.set_loading(false);
+        leaflet.set_visible_child_name(MAIN_PAGE);
+    });
+
+    let leaflet = content_leaflet.clone();
+    let detail = connection_detail_page.clone();
+    connection_list_page.connect_selected(move |connection| {
+        detail.set_connection(connection);
+        leaflet.set_visible_child_name(CONNECTION_DETAIL_PAGE);
+    });
+
+    let leaflet = content_leaflet.clone();
+    let page = search_page.clone();
+    let parent = window.clone();
+    receiver.attach(None, move |msg| {
+        match msg {
+            SearchMessage::Finished(id, kind, data, result) => {
+                if id != *current_search.lock().unwrap() {
+                    // the search was cancelled or replaced by a newer one
+                    return glib::Continue(true);
+                }
+
+                page.set_searching(false);
+                connection_list_page.set_loading(false);
+                connection_list_page.set_paging(false);
+
+                match (kind, result) {
+                    (SearchKind::New, Ok(connections)) => {
+                        // only searches which found something are worth repeating
+                        history.add(&data);
+                        connection_list_page.set_connections(connections);
+                    }
+                    (SearchKind::New, Err(error)) => {
+                        connection_list_page.set_connections(vec![]);
+                        leaflet.set_visible_child_name(MAIN_PAGE);
+                        let retry_page = page.clone();
+                        let retry = move || retry_page.search();
+                        show_search_error(&error, &parent, &page, &leaflet, retry);
+                    }
+                    (_, Ok(connections)) => connection_list_page.add_connections(connections),
+                    (_, Err(Error::NoConnections)) => {}
+                    (_, Err(error)) => {
+                        // a failed page is loaded again, not the whole search
+                        let list = connection_list_page.clone();
+                        let search_id = current_search.clone();
+                        let backend = backend.clone();
+                        let sender = sender.clone();
+                        let retry = move || {
+                            list.set_paging(true);
+                            spawn_search(&backend, &sender, &search_id, kind, data.clone());
+                        };
+                        show_search_error(&error, &parent, &page, &leaflet, retry);
+                    }
+                }
+            }
         }
+
+        glib::Continue(true)
     });
 
     let leaflet = content_leaflet.clone();
@@ -136,6 +407,184 @@ fn build_ui(app: &gtk::Application) {
         leaflet.set_visible_child_name(MAIN_PAGE);
     });
 
+    let leaflet = content_leaflet.clone();
+    detail_back_button.connect_clicked(move |_| {
+        leaflet.set_visible_child_name(CONNECTION_LIST_PAGE);
+    });
+
+    let leaflet = content_leaflet.clone();
+    stationboard_button.connect_clicked(move |_| {
+        leaflet.set_visible_child_name(STATIONBOARD_PAGE);
+    });
+
+    let leaflet = content_leaflet.clone();
+    stationboard_back_button.connect_clicked(move |_| {
+        leaflet.set_visible_child_name(MAIN_PAGE);
+    });
+
     window.add(&content_leaflet);
     window.show_all();
 }
+
+fn create_backend(settings: &Settings) -> Arc<dyn TimetableBackend> {
+    let base_url = settings.get_effective_api_base_url();
+    Arc::new(OpendataBackend::with_base_url(&base_url))
+}
+
+fn spawn_search(
+    backend: &Arc<dyn TimetableBackend>,
+    sender: &glib::Sender<SearchMessage>,
+    current_search: &Arc<Mutex<usize>>,
+    kind: SearchKind,
+    request: SearchConnectionRequest,
+) {
+    let id = {
+        let mut current_search = current_search.lock().unwrap();
+        *current_search += 1;
+        *current_search
+    };
+
+    let backend = backend.clone();
+    let sender = sender.clone();
+    thread::spawn(move || {
+        let result = backend.search_connection(request.clone());
+        let _ = sender.send(SearchMessage::Finished(id, kind, request, result));
+    });
+}
+
+fn get_search_error_text(error: &Error) -> (String, String) {
+    match error {
+        Error::NoNetwork(_) => (
+            "No connection to the timetable service".to_owned(),
+            "Please verify that you are connected\nto the internet and then retry.".to_owned(),
+        ),
+        Error::Timeout(_) => (
+            "The timetable service did not respond".to_owned(),
+            "The service is slow or unreachable.\nPlease retry in a moment.".to_owned(),
+        ),
+        Error::Status(429) => (
+            "Too many requests".to_owned(),
+            "The timetable service limits the number\nof searches. Please retry in a moment.".to_owned(),
+        ),
+        Error::Status(status) if *status >= 500 => (
+            "The timetable service is unavailable".to_owned(),
+            format!("It answered with status {}.\nPlease retry later.", status),
+        ),
+        Error::Status(status) => (
+            "The search was rejected".to_owned(),
+            format!("The timetable service answered with\nstatus {}. Please check your input.", status),
+        ),
+        Error::Schema(_) => (
+            "Unexpected response".to_owned(),
+            "The timetable service answered in an\nunknown format. Please check for an\nupdate of this app.".to_owned(),
+        ),
+        Error::UnknownStation(name) => (
+            "Unknown station".to_owned(),
+            format!("No station named \"{}\" was found.\nPlease check the spelling.", name),
+        ),
+        Error::AmbiguousStation { query, .. } => (
+            "Which station did you mean?".to_owned(),
+            format!("\"{}\" matches several stations.", query),
+        ),
+        Error::NoConnections => (
+            "No connections found".to_owned(),
+            "Try a different time or remove\nsome of the via stations.".to_owned(),
+        ),
+        Error::Http(_) | Error::Io(_) => ("Search failed".to_owned(), error.to_string()),
+    }
+}
+
+fn show_favorites_error(message: &str, window: &gtk::ApplicationWindow) {
+    let dialog = gtk::MessageDialogBuilder::new()
+        .modal(true)
+        .transient_for(window)
+        .message_type(gtk::MessageType::Error)
+        .title("Error")
+        .text("Favorites")
+        .secondary_text(message)
+        .buttons(gtk::ButtonsType::Close)
+        .build();
+
+    dialog.connect_response(|d, _| unsafe {
+        d.destroy();
+    });
+
+    dialog.show_all();
+}
+
+fn show_search_error<F>(
+    error: &Error,
+    window: &gtk::ApplicationWindow,
+    search_page: &SearchWidget,
+    leaflet: &libhandy::Leaflet,
+    retry: F,
+) where
+    F: Fn() + 'static,
+{
+    let (text, secondary_text) = get_search_error_text(error);
+
+    let dialog = gtk::MessageDialogBuilder::new()
+        .modal(true)
+        .transient_for(window)
+        .message_type(gtk::MessageType::Error)
+        .title("Error")
+        .text(&text)
+        .secondary_text(&secondary_text)
+        .build();
+
+    let mut candidates = vec![];
+    match error {
+        Error::AmbiguousStation {
+            candidates: names, ..
+        } => {
+            for (index, name) in names.iter().take(MAX_STATION_CANDIDATES).enumerate() {
+                dialog.add_button(name, gtk::ResponseType::Other(index as u16));
+                candidates.push(name.clone());
+            }
+        }
+        Error::UnknownStation(_) | Error::NoConnections => {
+            dialog.add_button("Edit search", gtk::ResponseType::Other(RESPONSE_EDIT));
+        }
+        _ if error.is_transient() => {
+            dialog.add_button("Retry", gtk::ResponseType::Other(RESPONSE_RETRY));
+        }
+        _ => {}
+    }
+
+    dialog.add_button("Close", gtk::ResponseType::Close);
+
+    let unknown_station = match error {
+        Error::UnknownStation(name) => Some(name.clone()),
+        Error::AmbiguousStation { query, .. } => Some(query.clone()),
+        _ => None,
+    };
+
+    let search_page = search_page.clone();
+    let leaflet = leaflet.clone();
+    dialog.connect_response(move |d, response| {
+        unsafe {
+            d.destroy();
+        }
+
+        match response {
+            gtk::ResponseType::Other(RESPONSE_RETRY) => retry(),
+            gtk::ResponseType::Other(RESPONSE_EDIT) => {
+                leaflet.set_visible_child_name(MAIN_PAGE);
+                if let Some(name) = &unknown_station {
+                    search_page.focus_location(name);
+                }
+            }
+            gtk::ResponseType::Other(index) => {
+                if let (Some(query), Some(candidate)) =
+                    (&unknown_station, candidates.get(index as usize))
+                {
+                    search_page.replace_location(query, candidate);
+                    search_page.search();
+                }
+            }
+            _ => {}
+        }
+    });
+
+    dialog.show_all();
+}
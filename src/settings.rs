@@ -0,0 +1,87 @@
+use std::path;
+
+use crate::api::{OpendataBackend, Transportation, DEFAULT_BASE_URL};
+use crate::dirs;
+
+const SETTINGS_FILE: &str = "settings";
+
+pub struct Settings {
+    settings_file: Option<path::PathBuf>,
+    key_file: glib::KeyFile,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        let key_file = glib::KeyFile::new();
+        let settings_file = dirs::config_dir().map(|dir| dir.join(SETTINGS_FILE));
+
+        if let Some(file) = &settings_file {
+            let flags = glib::KeyFileFlags::all();
+            let _ = key_file.load_from_file(file, flags);
+        }
+
+        Self {
+            settings_file,
+            key_file,
+        }
+    }
+
+    /// Settings which start empty and are never written to a file.
+    #[cfg(test)]
+    pub(crate) fn in_memory() -> Self {
+        Self {
+            settings_file: None,
+            key_file: glib::KeyFile::new(),
+        }
+    }
+
+    fn store(&self) {
+        if let Some(file) = &self.settings_file {
+            // settings are a convenience, failing to persist them is not fatal
+            let _ = self.key_file.save_to_file(file);
+        }
+    }
+
+    pub fn get_api_base_url(&self) -> Option<String> {
+        match self.key_file.get_string("Api", "BaseUrl") {
+            Ok(url) if !url.is_empty() => Some(url.as_str().to_owned()),
+            _ => None,
+        }
+    }
+
+    /// The base URL to query, `SBB_API_URL` takes precedence over the settings file.
+    pub fn get_effective_api_base_url(&self) -> String {
+        OpendataBackend::base_url_from_env()
+            .or_else(|| self.get_api_base_url())
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_owned())
+    }
+
+    pub fn get_transportations(&self) -> Vec<Transportation> {
+        match self.key_file.get_string("Search", "Transportations") {
+            Ok(names) => names
+                .split(';')
+                .filter_map(Transportation::from_name)
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    pub fn set_transportations(&self, transportations: &[Transportation]) {
+        let names: Vec<&str> = transportations.iter().map(|t| t.name()).collect();
+        self.key_file
+            .set_string("Search", "Transportations", &names.join(";"));
+        self.store();
+    }
+
+    pub fn get_direct_only(&self) -> bool {
+        self.key_file
+            .get_boolean("Search", "DirectOnly")
+            .unwrap_or(false)
+    }
+
+    pub fn set_direct_only(&self, direct_only: bool) {
+        self.key_file
+            .set_boolean("Search", "DirectOnly", direct_only);
+        self.store();
+    }
+}
@@ -0,0 +1,473 @@
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+pub const FORMAT_VERSION: u32 = 1;
+
+// query words shorter than this must match exactly, longer ones may contain a typo
+const MIN_FUZZY_LENGTH: usize = 4;
+
+const NAME_COLUMNS: &[&str] = &[
+    "stop_name",
+    "designationofficial",
+    "dst-bezeichnung-offiziell",
+    "bezeichnung_offiziell",
+    "name",
+];
+const ID_COLUMNS: &[&str] = &["stop_id", "number", "dst-nr", "didok", "bpuic"];
+const LATITUDE_COLUMNS: &[&str] = &["stop_lat", "wgs84north", "n_wgs84", "lat"];
+const LONGITUDE_COLUMNS: &[&str] = &["stop_lon", "wgs84east", "e_wgs84", "lon"];
+
+/// A station of the offline directory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StationRecord {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+    /// The normalized name which is matched against queries.
+    #[serde(default)]
+    key: String,
+}
+
+/// The stations of a GTFS `stops.txt` or a DiDok/service points CSV, ready for lookups.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StationDirectory {
+    pub version: u32,
+    /// Sorted by the normalized name.
+    pub stations: Vec<StationRecord>,
+    /// Every word of the normalized names with the position of its station, sorted.
+    #[serde(skip)]
+    words: Vec<(String, usize)>,
+}
+
+impl StationDirectory {
+    pub fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+        let mut directory: Self = serde_json::from_str(text)?;
+
+        // older or hand-written files might lack the precomputed keys
+        if directory.stations.iter().any(|s| s.key.is_empty()) {
+            directory.build_index();
+        } else {
+            directory.index_words();
+        }
+
+        Ok(directory)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Reads a GTFS `stops.txt` or a DiDok/service points CSV, the columns are detected by name.
+    pub fn from_csv(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .filter(|l| !l.trim().is_empty());
+
+        let header = lines.next().ok_or("The file is empty")?;
+        let delimiter = match header.matches(';').count() > header.matches(',').count() {
+            true => ';',
+            false => ',',
+        };
+
+        let columns: Vec<String> = split_csv_line(header, delimiter)
+            .iter()
+            .map(|c| c.trim().to_lowercase())
+            .collect();
+        let find = |names: &[&str]| columns.iter().position(|c| names.contains(&c.as_str()));
+
+        let name_column = find(NAME_COLUMNS).ok_or(
+            "The file contains no station names, expected a GTFS stops.txt or a service points CSV",
+        )?;
+        let id_column = find(ID_COLUMNS);
+        let latitude_column = find(LATITUDE_COLUMNS);
+        let longitude_column = find(LONGITUDE_COLUMNS);
+        let location_type_column = find(&["location_type"]);
+        let parent_column = find(&["parent_station"]);
+        let stop_point_column = find(&["stoppoint", "isstoppoint"]);
+
+        let mut stations: Vec<StationRecord> = vec![];
+        for line in lines {
+            let fields = split_csv_line(line, delimiter);
+            let get = |column: Option<usize>| {
+                column
+                    .and_then(|c| fields.get(c))
+                    .map(|f| f.trim())
+                    .filter(|f| !f.is_empty())
+            };
+
+            // GTFS lists every platform as a stop whose parent is the station
+            let is_platform = get(parent_column).is_some()
+                || matches!(get(location_type_column), Some(t) if t != "0" && t != "1");
+            let is_stop_point = match get(stop_point_column) {
+                Some(value) => value.eq_ignore_ascii_case("true") || value == "1",
+                None => true,
+            };
+
+            let name = match get(Some(name_column)) {
+                Some(name) if !is_platform && is_stop_point => name,
+                _ => continue,
+            };
+
+            stations.push(StationRecord {
+                name: name.to_owned(),
+                id: get(id_column).map(|i| i.to_owned()),
+                latitude: get(latitude_column).and_then(|l| l.parse().ok()),
+                longitude: get(longitude_column).and_then(|l| l.parse().ok()),
+                key: "".to_owned(),
+            });
+        }
+
+        if stations.is_empty() {
+            return Err("The file contains no stations".to_owned());
+        }
+
+        let mut directory = Self {
+            version: FORMAT_VERSION,
+            stations,
+            words: vec![],
+        };
+        directory.build_index();
+
+        Ok(directory)
+    }
+
+    fn build_index(&mut self) {
+        for station in &mut self.stations {
+            station.key = normalize(&station.name);
+        }
+
+        self.stations.sort_by(|a, b| a.key.cmp(&b.key));
+
+        // the same station is often listed once per data source or validity period
+        self.stations.dedup_by(|a, b| a.name == b.name);
+
+        self.index_words();
+    }
+
+    fn index_words(&mut self) {
+        self.words = self
+            .stations
+            .iter()
+            .enumerate()
+            .flat_map(|(index, station)| station.key.split(' ').map(move |w| (w.to_owned(), index)))
+            .collect();
+
+        self.words.sort();
+    }
+
+    pub fn len(&self) -> usize {
+        self.stations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty()
+    }
+
+    /// Finds the stations whose words start with the words of the query, in any order.
+    ///
+    /// Accents and case are ignored. Only the stations found in the word index are
+    /// looked at, so this is fast enough to run for every keystroke.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<&StationRecord> {
+        let query = normalize(query);
+        let tokens: Vec<&str> = query.split(' ').filter(|t| !t.is_empty()).collect();
+        if tokens.is_empty() || limit == 0 {
+            return vec![];
+        }
+
+        let mut candidates = self.find_word_prefix(tokens[0]);
+        for token in &tokens[1..] {
+            let others: HashSet<usize> = self.find_word_prefix(token).into_iter().collect();
+            candidates.retain(|index| others.contains(index));
+        }
+
+        let stations = candidates.into_iter().map(|index| &self.stations[index]);
+        Self::rank(stations, &query, &tokens, false, limit)
+    }
+
+    /// Like `search`, but longer words of the query may contain a single typo.
+    ///
+    /// This compares the query with every station and should not run on the main thread.
+    pub fn search_similar(&self, query: &str, limit: usize) -> Vec<&StationRecord> {
+        let query = normalize(query);
+        let tokens: Vec<&str> = query.split(' ').filter(|t| !t.is_empty()).collect();
+        if tokens.is_empty() || limit == 0 {
+            return vec![];
+        }
+
+        Self::rank(self.stations.iter(), &query, &tokens, true, limit)
+    }
+
+    /// The positions of the stations with a word starting with the token, in key order.
+    fn find_word_prefix(&self, token: &str) -> Vec<usize> {
+        let start = self
+            .words
+            .partition_point(|(word, _)| word.as_str() < token);
+        let mut indices: Vec<usize> = self.words[start..]
+            .iter()
+            .take_while(|(word, _)| word.starts_with(token))
+            .map(|(_, index)| *index)
+            .collect();
+
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    fn rank<'a, I>(
+        stations: I,
+        query: &str,
+        tokens: &[&str],
+        allow_typos: bool,
+        limit: usize,
+    ) -> Vec<&'a StationRecord>
+    where
+        I: Iterator<Item = &'a StationRecord>,
+    {
+        let mut matches: Vec<(usize, &StationRecord)> = stations
+            .filter_map(|station| {
+                let score = get_score(&station.key, query, tokens, allow_typos)?;
+                Some((score, station))
+            })
+            .collect();
+
+        // the stations are sorted by key, a stable sort keeps that order for equal scores
+        matches.sort_by_key(|(score, station)| (Reverse(*score), station.key.len()));
+        matches.truncate(limit);
+
+        matches.into_iter().map(|(_, station)| station).collect()
+    }
+}
+
+/// Lowercases the text, removes accents and replaces punctuation with single spaces.
+pub fn normalize(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+
+    for c in text.chars().flat_map(|c| c.to_lowercase()) {
+        let folded = match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => "a",
+            'æ' => "ae",
+            'ç' => "c",
+            'è' | 'é' | 'ê' | 'ë' => "e",
+            'ì' | 'í' | 'î' | 'ï' => "i",
+            'ñ' => "n",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => "o",
+            'œ' => "oe",
+            'ß' => "ss",
+            'ù' | 'ú' | 'û' | 'ü' => "u",
+            'ý' | 'ÿ' => "y",
+            c if c.is_alphanumeric() => {
+                normalized.push(c);
+                continue;
+            }
+            _ => " ",
+        };
+
+        if folded != " " || !normalized.ends_with(' ') {
+            normalized.push_str(folded);
+        }
+    }
+
+    normalized.trim().to_owned()
+}
+
+fn get_score(key: &str, query: &str, tokens: &[&str], allow_typos: bool) -> Option<usize> {
+    let mut score = 0;
+    for token in tokens {
+        let word_score = key
+            .split(' ')
+            .map(|w| get_word_score(w, token, allow_typos))
+            .max();
+
+        match word_score {
+            Some(word_score) if word_score > 0 => score += word_score,
+            _ => return None,
+        }
+    }
+
+    if key.starts_with(query) {
+        score += 10;
+    } else if key.starts_with(tokens[0]) {
+        score += 2;
+    }
+
+    Some(score)
+}
+
+fn get_word_score(word: &str, token: &str, allow_typos: bool) -> usize {
+    if word == token {
+        return 3;
+    }
+
+    if word.starts_with(token) {
+        return 2;
+    }
+
+    let length = token.chars().count();
+    if !allow_typos || length < MIN_FUZZY_LENGTH {
+        return 0;
+    }
+
+    // compare with prefixes one character shorter or longer to allow insertions and deletions
+    let word: Vec<char> = word.chars().collect();
+    let token: Vec<char> = token.chars().collect();
+    let is_close = (length - 1..=length + 1)
+        .filter(|l| *l <= word.len())
+        .any(|l| get_edit_distance(&word[..l], &token) <= 1);
+
+    match is_close {
+        true => 1,
+        false => 0,
+    }
+}
+
+/// The optimal string alignment distance, which counts swapped neighbours as one edit.
+fn get_edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+fn split_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut is_quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if is_quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => is_quoted = !is_quoted,
+            c if c == delimiter && !is_quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GTFS_STOPS: &str =
+        "\u{feff}stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station
+8503000,Zürich HB,47.37819,8.54019,1,
+8503000:0:7,Zürich HB,47.37819,8.54019,,8503000
+8503020,Zürich Hardbrücke,47.38517,8.51701,1,
+8502204,\"Zug, Bahnhof\",47.17363,8.51534,,
+8505000,Luzern,47.05017,8.31018,1,
+";
+
+    const SERVICE_POINTS: &str = "number;designationOfficial;wgs84East;wgs84North;stopPoint
+8507000;Bern;7.43911;46.94883;true
+8507000;Bern;7.43911;46.94883;true
+8590000;Bern, Bahnhof Betriebsstelle;7.43;46.94;false
+8503103;Genève-Aéroport;6.11;46.23;true
+";
+
+    fn names(stations: Vec<&StationRecord>) -> Vec<&str> {
+        stations.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn it_reads_the_stations_of_a_gtfs_stops_file() {
+        let directory = StationDirectory::from_csv(GTFS_STOPS).unwrap();
+
+        assert_eq!(directory.len(), 4);
+        let zug = &directory.search("Zug", 1)[0];
+        assert_eq!(zug.name, "Zug, Bahnhof");
+        assert_eq!(zug.id.as_deref(), Some("8502204"));
+        assert_eq!(zug.latitude, Some(47.17363));
+    }
+
+    #[test]
+    fn it_reads_the_stop_points_of_a_service_points_file() {
+        let directory = StationDirectory::from_csv(SERVICE_POINTS).unwrap();
+
+        assert_eq!(directory.len(), 2);
+        assert_eq!(names(directory.search("bern", 5)), vec!["Bern"]);
+    }
+
+    #[test]
+    fn it_rejects_files_without_station_names() {
+        assert!(StationDirectory::from_csv("a,b\n1,2\n").is_err());
+        assert!(StationDirectory::from_csv("").is_err());
+    }
+
+    #[test]
+    fn it_matches_word_prefixes_without_accents_in_any_order() {
+        let directory = StationDirectory::from_csv(GTFS_STOPS).unwrap();
+
+        assert_eq!(
+            names(directory.search("zurich", 5)),
+            vec!["Zürich HB", "Zürich Hardbrücke"]
+        );
+        assert_eq!(
+            names(directory.search("hardbr zür", 5)),
+            vec!["Zürich Hardbrücke"]
+        );
+
+        let directory = StationDirectory::from_csv(SERVICE_POINTS).unwrap();
+        assert_eq!(
+            names(directory.search("geneve aero", 5)),
+            vec!["Genève-Aéroport"]
+        );
+    }
+
+    #[test]
+    fn it_tolerates_a_typo_in_longer_words() {
+        let directory = StationDirectory::from_csv(GTFS_STOPS).unwrap();
+
+        assert_eq!(names(directory.search_similar("Luzren", 5)), vec!["Luzern"]);
+        assert_eq!(
+            names(directory.search_similar("Lzu", 5)),
+            Vec::<&str>::new()
+        );
+        assert_eq!(names(directory.search("Luzren", 5)), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn it_survives_a_round_trip_through_json() {
+        let directory = StationDirectory::from_csv(GTFS_STOPS).unwrap();
+        let parsed = StationDirectory::from_json(&directory.to_json()).unwrap();
+
+        assert_eq!(parsed, directory);
+    }
+
+    #[test]
+    fn it_normalizes_names() {
+        assert_eq!(normalize("St. Gallen, Bahnhof"), "st gallen bahnhof");
+        assert_eq!(normalize("Zürich  Flughafen"), "zurich flughafen");
+        assert_eq!(normalize("Genève-Aéroport"), "geneve aeroport");
+    }
+}
@@ -0,0 +1,57 @@
+use std::fs;
+use std::path;
+
+use crate::dirs;
+
+mod directory;
+
+pub use directory::{normalize, StationDirectory, StationRecord, FORMAT_VERSION};
+
+const INDEX_FILE: &str = "stations.json";
+
+/// Keeps the imported station directory as an index in the data directory.
+#[derive(Clone)]
+pub struct StationDirectoryStore {
+    index_file: Option<path::PathBuf>,
+}
+
+impl StationDirectoryStore {
+    pub fn new() -> Self {
+        Self {
+            index_file: dirs::data_dir().map(|dir| dir.join(INDEX_FILE)),
+        }
+    }
+
+    /// Reads the index, returns `None` if no directory was imported yet.
+    pub fn load(&self) -> Result<Option<StationDirectory>, String> {
+        let index_file = match &self.index_file {
+            Some(file) if file.exists() => file,
+            _ => return Ok(None),
+        };
+
+        let text = fs::read_to_string(index_file).map_err(|e| e.to_string())?;
+        let directory = StationDirectory::from_json(&text).map_err(|e| e.to_string())?;
+
+        Ok(Some(directory))
+    }
+
+    /// Indexes a GTFS `stops.txt` or a service points CSV and replaces the current index.
+    pub fn import(&self, source: &path::Path) -> Result<StationDirectory, String> {
+        let index_file = self.index_file.as_ref().ok_or("No data directory")?;
+
+        let bytes = fs::read(source).map_err(|e| e.to_string())?;
+        let directory = StationDirectory::from_csv(&String::from_utf8_lossy(&bytes))?;
+
+        glib::file_set_contents(index_file, directory.to_json().as_bytes())
+            .map_err(|e| e.to_string())?;
+
+        Ok(directory)
+    }
+
+    pub fn remove(&self) -> Result<(), String> {
+        match &self.index_file {
+            Some(file) if file.exists() => fs::remove_file(file).map_err(|e| e.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
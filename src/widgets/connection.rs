@@ -1,7 +1,13 @@
+use chrono::offset::FixedOffset;
 use chrono::DateTime;
+use gio::prelude::*;
 use gtk::prelude::*;
 
+use std::fs;
+
 use crate::api::Connection;
+use crate::format;
+use crate::ical;
 use crate::widgets::SectionWidget;
 
 pub struct ConnectionWidget {
@@ -18,7 +24,42 @@ impl ConnectionWidget {
         label.set_margin_start(5);
         label.set_margin_end(5);
         label.set_markup(&Self::get_label_text(connection));
-        container.add(&label);
+        label.set_hexpand(true);
+
+        let menu = gio::Menu::new();
+        menu.append(Some("Copy as text"), Some("connection.copy-text"));
+        menu.append(Some("Copy as JSON"), Some("connection.copy-json"));
+        menu.append(Some("Copy as CSV"), Some("connection.copy-csv"));
+        menu.append(Some("Add to calendar"), Some("connection.add-to-calendar"));
+
+        let menu_button = gtk::MenuButtonBuilder::new()
+            .relief(gtk::ReliefStyle::None)
+            .valign(gtk::Align::Center)
+            .margin_end(5)
+            .tooltip_text("Share")
+            .menu_model(&menu)
+            .build();
+        let icon = gtk::Image::from_icon_name(Some("view-more-symbolic"), gtk::IconSize::Menu);
+        menu_button.set_image(Some(&icon));
+
+        let header = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        header.add(&label);
+        header.add(&menu_button);
+        container.add(&header);
+
+        let actions = gio::SimpleActionGroup::new();
+        Self::add_copy_action(
+            &actions,
+            "copy-text",
+            connection,
+            format::connections_to_text,
+        );
+        Self::add_copy_action(&actions, "copy-json", connection, |c| {
+            format::connections_to_json(c).expect("Failed to serialize connection")
+        });
+        Self::add_copy_action(&actions, "copy-csv", connection, format::connections_to_csv);
+        Self::add_calendar_action(&actions, &container, connection);
+        container.insert_action_group("connection", Some(&actions));
 
         let seperator = gtk::Separator::new(gtk::Orientation::Horizontal);
         container.add(&seperator);
@@ -34,27 +75,111 @@ impl ConnectionWidget {
         Self { container }
     }
 
-    fn get_label_text(connection: &Connection) -> String {
+    fn add_copy_action<F>(
+        actions: &gio::SimpleActionGroup,
+        name: &str,
+        connection: &Connection,
+        render: F,
+    ) where
+        F: Fn(&[Connection]) -> String + 'static,
+    {
+        let connections = vec![connection.clone()];
+        let action = gio::SimpleAction::new(name, None);
+        action.connect_activate(move |_, _| {
+            let clipboard = gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD);
+            clipboard.set_text(&render(&connections));
+        });
+
+        actions.add_action(&action);
+    }
+
+    fn add_calendar_action(
+        actions: &gio::SimpleActionGroup,
+        container: &gtk::Box,
+        connection: &Connection,
+    ) {
+        let action = gio::SimpleAction::new("add-to-calendar", None);
+        action.set_enabled(connection.from.departure.is_some() && connection.to.arrival.is_some());
+
+        // a weak reference avoids a cycle between the container and its actions
+        let container = container.downgrade();
+        let connection = connection.clone();
+        action.connect_activate(move |_, _| {
+            if let Some(container) = container.upgrade() {
+                Self::save_calendar(&container, &connection);
+            }
+        });
+
+        actions.add_action(&action);
+    }
+
+    fn save_calendar(container: &gtk::Box, connection: &Connection) {
+        let calendar = match ical::connection_to_ics(connection) {
+            Some(calendar) => calendar,
+            None => return,
+        };
+
+        let window = container
+            .get_toplevel()
+            .and_then(|w| w.downcast::<gtk::Window>().ok());
+
+        let dialog = gtk::FileChooserNative::new(
+            Some("Add to calendar"),
+            window.as_ref(),
+            gtk::FileChooserAction::Save,
+            Some("_Save"),
+            Some("_Cancel"),
+        );
+        dialog.set_current_name(ical::suggested_file_name(connection));
+        dialog.set_do_overwrite_confirmation(true);
+
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("Calendar files"));
+        filter.add_mime_type("text/calendar");
+        filter.add_pattern("*.ics");
+        dialog.add_filter(&filter);
+
+        if dialog.run() != gtk::ResponseType::Accept {
+            return;
+        }
+
+        let path = match dialog.get_filename() {
+            Some(path) => path,
+            None => return,
+        };
+
+        if let Err(error) = fs::write(&path, calendar) {
+            let dialog = gtk::MessageDialogBuilder::new()
+                .modal(true)
+                .message_type(gtk::MessageType::Error)
+                .buttons(gtk::ButtonsType::Close)
+                .text("Failed to save the calendar file")
+                .secondary_text(&error.to_string())
+                .build();
+            dialog.set_transient_for(window.as_ref());
+            dialog.connect_response(|d, _| unsafe {
+                d.destroy();
+            });
+            dialog.show_all();
+        }
+    }
+
+    pub(crate) fn get_label_text(connection: &Connection) -> String {
         format!(
-            "<big><b>{} {} - {} {}</b></big>",
+            "<big><b>{} {} - {} {}</b></big>\n<small>{} · {}</small>",
             Self::format_time(&connection.from.departure),
             connection.from.station.name,
             Self::format_time(&connection.to.arrival),
-            connection.to.station.name
+            connection.to.station.name,
+            format::format_duration(&connection.duration),
+            format::format_transfers(connection.transfers)
         )
     }
 
-    fn format_time(input: &Option<String>) -> String {
-        let format = "%Y-%m-%dT%H:%M:%S%z";
-
-        let time = match input.as_ref() {
-            Some(time) => time,
-            None => return "".to_owned(),
-        };
-
-        match DateTime::parse_from_str(time, format) {
-            Ok(rfc3339) => rfc3339.format("%H:%M").to_string(),
-            Err(_) => "".to_owned(),
+    fn format_time(input: &Option<DateTime<FixedOffset>>) -> String {
+        match input {
+            Some(time) => time.format("%H:%M").to_string(),
+            None => "".to_owned(),
         }
     }
 }
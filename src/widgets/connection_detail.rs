@@ -0,0 +1,169 @@
+use gtk::prelude::*;
+
+use crate::api::{Connection, Section, Stop};
+use crate::format;
+use crate::widgets::{ConnectionWidget, SectionWidget};
+
+#[derive(Clone)]
+pub struct ConnectionDetailWidget {
+    pub container: gtk::ScrolledWindow,
+    main_box: gtk::Box,
+}
+
+impl ConnectionDetailWidget {
+    pub fn new() -> Self {
+        let hadjust: Option<&gtk::Adjustment> = None;
+        let vadjust: Option<&gtk::Adjustment> = None;
+        let container = gtk::ScrolledWindow::new(hadjust, vadjust);
+        container.set_vexpand(true);
+        container.set_hexpand(true);
+
+        let main_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        container.add(&main_box);
+
+        Self {
+            container,
+            main_box,
+        }
+    }
+
+    pub fn set_connection(&self, connection: &Connection) {
+        self.clear();
+
+        let label = gtk::Label::new(None);
+        label.set_margin_top(10);
+        label.set_margin_bottom(10);
+        label.set_margin_start(5);
+        label.set_margin_end(5);
+        label.set_markup(&ConnectionWidget::get_label_text(connection));
+        self.main_box.add(&label);
+
+        let mut sections = connection.sections.iter().peekable();
+        while let Some(section) = sections.next() {
+            let seperator = gtk::Separator::new(gtk::Orientation::Horizontal);
+            self.main_box.add(&seperator);
+
+            self.main_box.add(&Self::create_section_grid(section));
+
+            if let Some(next) = sections.peek() {
+                if let Some(label) = Self::create_transfer_label(section, next) {
+                    let seperator = gtk::Separator::new(gtk::Orientation::Horizontal);
+                    self.main_box.add(&seperator);
+                    self.main_box.add(&label);
+                }
+            }
+        }
+
+        self.main_box.show_all();
+    }
+
+    fn create_section_grid(section: &Section) -> gtk::Grid {
+        let grid = gtk::Grid::new();
+        grid.set_hexpand(true);
+
+        let title = SectionWidget::create_label_with_default_margin();
+        title.set_markup(&Self::get_section_title(section));
+        title.set_xalign(0.0);
+        grid.attach(&title, 0, 0, 4, 1);
+
+        let mut row = 1;
+        Self::attach_stop(&grid, row, &section.departure, true);
+
+        for stop in section.intermediate_stops() {
+            row += 1;
+            Self::attach_stop(&grid, row, stop, false);
+        }
+
+        row += 1;
+        Self::attach_stop(&grid, row, &section.arrival, true);
+
+        grid
+    }
+
+    fn attach_stop(grid: &gtk::Grid, row: i32, stop: &Stop, is_highlighted: bool) {
+        let arrival = SectionWidget::create_label_with_default_margin();
+        arrival.set_markup(&SectionWidget::format_time_with_prognosis(
+            &stop.arrival,
+            &stop.expected_arrival(),
+            &stop.delay,
+        ));
+        grid.attach(&arrival, 0, row, 1, 1);
+
+        let departure = SectionWidget::create_label_with_default_margin();
+        departure.set_markup(&SectionWidget::format_time_with_prognosis(
+            &stop.departure,
+            &stop.expected_departure(),
+            &stop.delay,
+        ));
+        grid.attach(&departure, 1, row, 1, 1);
+
+        let station = SectionWidget::create_label_with_default_margin();
+        station.set_text(&stop.station.name);
+        station.set_hexpand(true);
+        station.set_xalign(0.0);
+        if is_highlighted {
+            station.set_markup(&format!(
+                "<b>{}</b>",
+                glib::markup_escape_text(&stop.station.name)
+            ));
+        }
+        grid.attach(&station, 2, row, 1, 1);
+
+        let platform = SectionWidget::create_stop_platform_label(stop);
+        grid.attach(&platform, 3, row, 1, 1);
+    }
+
+    fn get_section_title(section: &Section) -> String {
+        if let Some(journey) = section.journey.as_ref() {
+            let operator = match &journey.operator {
+                Some(operator) => {
+                    format!(" <small>({})</small>", glib::markup_escape_text(operator))
+                }
+                None => "".to_owned(),
+            };
+
+            return format!(
+                "<b>{} {}</b> to {}{}",
+                glib::markup_escape_text(&journey.category),
+                glib::markup_escape_text(&journey.number),
+                glib::markup_escape_text(&journey.to),
+                operator
+            );
+        }
+
+        if let Some(walk) = section.walk.as_ref() {
+            return format!("<b>Walk {} min</b>", walk.duration / 60);
+        }
+
+        "".to_owned()
+    }
+
+    fn create_transfer_label(section: &Section, next: &Section) -> Option<gtk::Label> {
+        // walks already show their duration and need no transfer time
+        if section.walk.is_some() || next.walk.is_some() {
+            return None;
+        }
+
+        let transfer_time = section.transfer_time(next)?;
+
+        let color = match transfer_time.num_minutes() {
+            m if m < 0 => "red",
+            _ => "gray",
+        };
+
+        let label = SectionWidget::create_label_with_default_margin();
+        label.set_markup(&format!(
+            "<span foreground=\"{}\"><i>Transfer {}</i></span>",
+            color,
+            format::format_duration(&transfer_time)
+        ));
+
+        Some(label)
+    }
+
+    fn clear(&self) {
+        self.main_box.foreach(|child| {
+            self.main_box.remove(child);
+        });
+    }
+}
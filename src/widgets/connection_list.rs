@@ -1,11 +1,25 @@
+use gio::prelude::*;
 use gtk::prelude::*;
 
-use crate::api::Connection;
+use std::sync::{Arc, Mutex};
+
+use crate::api::{self, Connection};
 use crate::widgets::ConnectionWidget;
 
+#[derive(Clone)]
 pub struct ConnectionListWidget {
     pub container: gtk::ScrolledWindow,
-    main_box: gtk::Box,
+    main_box: gtk::ListBox,
+    loading_box: gtk::Box,
+    spinner: gtk::Spinner,
+    cancel_button: gtk::Button,
+    earlier_button: gtk::Button,
+    later_button: gtk::Button,
+    connections: Arc<Mutex<Vec<Connection>>>,
+    cancel: gio::SimpleAction,
+    load_earlier: gio::SimpleAction,
+    load_later: gio::SimpleAction,
+    selected: gio::SimpleAction,
 }
 
 impl ConnectionListWidget {
@@ -14,24 +28,176 @@ impl ConnectionListWidget {
         container.set_vexpand(true);
         container.set_hexpand(true);
 
-        let main_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
-        container.add(&main_box);
+        let spinner = gtk::Spinner::new();
+        spinner.set_margin_top(20);
+        spinner.set_margin_bottom(10);
+        spinner.set_size_request(32, 32);
+
+        let cancel_button = gtk::Button::with_label("Cancel");
+        cancel_button.set_halign(gtk::Align::Center);
+        cancel_button.set_margin_bottom(10);
+
+        let loading_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        loading_box.add(&spinner);
+        loading_box.add(&cancel_button);
+        loading_box.show_all();
+        loading_box.set_no_show_all(true);
+        loading_box.hide();
+
+        let earlier_button = Self::create_paging_button("Earlier connections");
+        let later_button = Self::create_paging_button("Later connections");
 
-        Self {
+        let main_box = gtk::ListBox::new();
+        main_box.set_selection_mode(gtk::SelectionMode::None);
+
+        let outer_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        outer_box.add(&loading_box);
+        outer_box.add(&earlier_button);
+        outer_box.add(&main_box);
+        outer_box.add(&later_button);
+        container.add(&outer_box);
+
+        let widget = Self {
             container,
             main_box,
-        }
+            loading_box,
+            spinner,
+            cancel_button,
+            earlier_button,
+            later_button,
+            connections: Arc::new(Mutex::new(vec![])),
+            cancel: gio::SimpleAction::new("cancel", None),
+            load_earlier: gio::SimpleAction::new("load-earlier", None),
+            load_later: gio::SimpleAction::new("load-later", None),
+            selected: gio::SimpleAction::new("selected", Some(0u32.to_variant().type_())),
+        };
+
+        widget.setup_event_handlers();
+
+        widget
+    }
+
+    fn setup_event_handlers(&self) {
+        let cancel = self.cancel.clone();
+        self.cancel_button.connect_clicked(move |_| {
+            cancel.activate(None);
+        });
+
+        let load_earlier = self.load_earlier.clone();
+        self.earlier_button.connect_clicked(move |_| {
+            load_earlier.activate(None);
+        });
+
+        let load_later = self.load_later.clone();
+        self.later_button.connect_clicked(move |_| {
+            load_later.activate(None);
+        });
+
+        let selected = self.selected.clone();
+        self.main_box.connect_row_activated(move |_, row| {
+            let index = row.get_index() as u32;
+            selected.activate(Some(&index.to_variant()));
+        });
     }
 
     pub fn set_connections(&self, connections: Vec<Connection>) {
+        *self.connections.lock().unwrap() = connections;
+        self.update_connections();
+    }
+
+    /// Merges earlier or later connections into the list, dropping duplicates.
+    pub fn add_connections(&self, connections: Vec<Connection>) {
+        {
+            let mut current = self.connections.lock().unwrap();
+            let existing = current.drain(..).collect();
+            *current = api::merge_connections(existing, connections);
+        }
+
+        self.update_connections();
+    }
+
+    pub fn get_first_connection(&self) -> Option<Connection> {
+        self.connections.lock().unwrap().first().cloned()
+    }
+
+    pub fn get_last_connection(&self) -> Option<Connection> {
+        self.connections.lock().unwrap().last().cloned()
+    }
+
+    fn update_connections(&self) {
         self.clear();
 
+        let connections = self.connections.lock().unwrap();
         for connection in connections.iter() {
             let connection_widget = ConnectionWidget::new(&connection);
             self.main_box.add(&connection_widget.container);
         }
 
         self.main_box.show_all();
+        self.set_paging_visible(!connections.is_empty());
+    }
+
+    pub fn set_loading(&self, is_loading: bool) {
+        if is_loading {
+            self.set_connections(vec![]);
+            self.spinner.start();
+            self.loading_box.show();
+        } else {
+            self.spinner.stop();
+            self.loading_box.hide();
+        }
+    }
+
+    pub fn set_paging(&self, is_paging: bool) {
+        self.earlier_button.set_sensitive(!is_paging);
+        self.later_button.set_sensitive(!is_paging);
+    }
+
+    fn set_paging_visible(&self, is_visible: bool) {
+        self.earlier_button.set_visible(is_visible);
+        self.later_button.set_visible(is_visible);
+    }
+
+    pub fn connect_cancel<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.cancel.connect_activate(move |_, _| {
+            callback();
+        });
+    }
+
+    pub fn connect_load_earlier<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.load_earlier.connect_activate(move |_, _| {
+            callback();
+        });
+    }
+
+    pub fn connect_load_later<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.load_later.connect_activate(move |_, _| {
+            callback();
+        });
+    }
+
+    pub fn connect_selected<F>(&self, callback: F)
+    where
+        F: Fn(&Connection) + 'static,
+    {
+        let connections = self.connections.clone();
+        self.selected.connect_activate(move |_, index| {
+            let index = index.and_then(|i| i.get::<u32>()).unwrap() as usize;
+            let connection = connections.lock().unwrap().get(index).cloned();
+
+            if let Some(connection) = connection {
+                callback(&connection);
+            }
+        });
     }
 
     fn clear(&self) {
@@ -40,6 +206,17 @@ impl ConnectionListWidget {
         });
     }
 
+    fn create_paging_button(caption: &str) -> gtk::Button {
+        let button = gtk::ButtonBuilder::new()
+            .label(caption)
+            .relief(gtk::ReliefStyle::None)
+            .margin(5)
+            .build();
+
+        button.set_no_show_all(true);
+        button
+    }
+
     fn create_scrolled_window() -> gtk::ScrolledWindow {
         let hadjust: Option<&gtk::Adjustment> = None;
         let vadjust: Option<&gtk::Adjustment> = None;
@@ -84,6 +84,11 @@ impl DateTimePickerPopover {
         *self.time.lock().unwrap()
     }
 
+    pub fn set_date_time(&self, time: Option<DateTime<Local>>) {
+        self.set_inputs_to_time(time);
+        self.set_time(time);
+    }
+
     pub fn get_popover(&self) -> &gtk::Popover {
         &self.popover
     }
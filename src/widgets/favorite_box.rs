@@ -2,8 +2,9 @@ use gtk::prelude::*;
 
 use std::sync::Arc;
 
-use crate::favorites::Favorites;
+use crate::favorites::{FavoriteStation, Favorites};
 use crate::string_event_handler::StringEventHandler;
+use crate::widgets::prompt;
 
 const STRING_TARGET_INFO: u32 = 0;
 const STRING_TARGET_NAME: &'static str = "STRING";
@@ -42,61 +43,107 @@ impl FavoriteBoxWidget {
         });
     }
 
-    fn enable_drag_an_drop_on_button(&self, button: &gtk::Button) {
+    /// A plain drag moves the favorite to the position of the one it is dropped on, a
+    /// drag with Ctrl+Shift selects both favorites as source and destination instead.
+    fn enable_drag_an_drop_on_button(&self, button: &gtk::Button, name: &str) {
         let targets = vec![gtk::TargetEntry::new(
             STRING_TARGET_NAME,
             gtk::TargetFlags::SAME_APP,
             STRING_TARGET_INFO,
         )];
+        let actions = gdk::DragAction::MOVE | gdk::DragAction::LINK;
 
         // enable dragging
-        button.drag_source_set(
-            gdk::ModifierType::MODIFIER_MASK,
-            &targets,
-            gdk::DragAction::COPY,
-        );
+        button.drag_source_set(gdk::ModifierType::MODIFIER_MASK, &targets, actions);
 
         // enable dropping
-        button.drag_dest_set(gtk::DestDefaults::ALL, &targets, gdk::DragAction::COPY);
-
-        // set drag data
-        button.connect_drag_data_get(|b, _, s, _, _| {
-            let caption = b.get_label().unwrap().as_str().to_owned();
+        button.drag_dest_set(gtk::DestDefaults::ALL, &targets, actions);
 
+        // set drag data, the label might be an alias
+        let source = name.to_owned();
+        button.connect_drag_data_get(move |_, _, s, _, _| {
             s.set(
                 &gdk::SELECTION_TYPE_STRING,
                 STRING_TARGET_INFO as i32,
-                &caption.into_bytes(),
+                source.as_bytes(),
             );
         });
 
         // read drag data
         let action = self.favorite_selected.clone();
-        button.connect_drag_data_received(move |b, _, _, _, s, _, _| {
-            let caption = b.get_label().unwrap();
-
-            if let Some(text) = s.get_text() {
+        let favorites = self.favorites.clone();
+        let destination = name.to_owned();
+        button.connect_drag_data_received(move |_, context, _, _, s, _, _| {
+            let text = match s.get_text() {
+                Some(text) => text.as_str().to_owned(),
+                None => return,
+            };
+
+            if context.get_selected_action() == gdk::DragAction::LINK {
                 // trigger the favorite_selected callback with the source
-                action.trigger(&text.as_str());
+                action.trigger(&text);
 
                 // trigger the favorite_selected callback with the destination
-                action.trigger(caption.as_str());
+                action.trigger(&destination);
+            } else {
+                favorites.move_to(&text, &destination);
+            }
+        });
+    }
+
+    fn enable_context_menu_on_button(&self, button: &gtk::Button, station: &FavoriteStation) {
+        let favorites = self.favorites.clone();
+        let station = station.clone();
+        button.connect_button_press_event(move |button, event| {
+            if event.get_button() != 3 {
+                return Inhibit(false);
             }
+
+            let rename_item = gtk::MenuItem::with_label("Rename…");
+            let favorites = favorites.clone();
+            let station = station.clone();
+            let parent = button.clone();
+            rename_item.connect_activate(move |_| {
+                let alias = prompt::ask_text(
+                    &parent,
+                    "Rename favorite",
+                    "_Rename",
+                    station.display_name(),
+                    Some(&station.name),
+                );
+
+                if let Some(alias) = alias {
+                    favorites.rename(&station.name, &alias);
+                }
+            });
+
+            let menu = gtk::Menu::new();
+            menu.set_attach_widget(Some(button));
+            menu.append(&rename_item);
+            menu.show_all();
+            menu.popup_at_pointer(Some(event));
+
+            Inhibit(true)
         });
     }
 
     fn update_favorites(&self) {
         self.clear();
 
-        for favorite in self.favorites.get() {
-            let button = gtk::Button::with_label(&favorite);
-            self.enable_drag_an_drop_on_button(&button);
+        for station in self.favorites.get_stations() {
+            let button = gtk::Button::with_label(station.display_name());
+            if station.alias.is_some() {
+                button.set_tooltip_text(Some(&station.name));
+            }
+
+            self.enable_drag_an_drop_on_button(&button, &station.name);
+            self.enable_context_menu_on_button(&button, &station);
 
             self.container.add(&button);
 
             let action = self.favorite_selected.clone();
             button.connect_clicked(move |_| {
-                action.trigger(&favorite);
+                action.trigger(&station.name);
             });
         }
 
@@ -0,0 +1,204 @@
+use gio::prelude::*;
+use gtk::prelude::*;
+
+use chrono::Local;
+
+use std::sync::{Arc, Mutex};
+
+use crate::history::{HistoryEntry, SearchHistory};
+
+const MAX_REVERSE_SUGGESTIONS: usize = 2;
+
+#[derive(Clone)]
+pub struct HistoryBoxWidget {
+    pub container: gtk::Box,
+    list: gtk::ListBox,
+    clear_button: gtk::Button,
+    history: Arc<SearchHistory>,
+    // the entries in the order of the rows, including the reverse suggestions
+    entries: Arc<Mutex<Vec<HistoryEntry>>>,
+    selected: gio::SimpleAction,
+}
+
+impl HistoryBoxWidget {
+    pub fn new(history: Arc<SearchHistory>) -> Self {
+        let title = gtk::Label::new(None);
+        title.set_markup("<b>Recent searches</b>");
+        title.set_xalign(0.0);
+        title.set_hexpand(true);
+
+        let clear_button =
+            gtk::Button::from_icon_name(Some("edit-clear-all-symbolic"), gtk::IconSize::Menu);
+        clear_button.set_relief(gtk::ReliefStyle::None);
+        clear_button.set_tooltip_text(Some("Clear history"));
+
+        let header = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        header.set_margin_start(5);
+        header.set_margin_end(5);
+        header.add(&title);
+        header.add(&clear_button);
+
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::None);
+
+        let hadjust: Option<&gtk::Adjustment> = None;
+        let vadjust: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(hadjust, vadjust);
+        scrolled_window.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled_window.set_vexpand(true);
+        scrolled_window.add(&list);
+
+        header.show_all();
+        scrolled_window.show_all();
+
+        // the window must not show the box while the history is empty
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        container.set_margin_top(5);
+        container.add(&header);
+        container.add(&scrolled_window);
+        container.set_no_show_all(true);
+
+        let widget = Self {
+            container,
+            list,
+            clear_button,
+            history,
+            entries: Arc::new(Mutex::new(vec![])),
+            selected: gio::SimpleAction::new("selected", Some(0u32.to_variant().type_())),
+        };
+
+        widget.setup_event_handlers();
+        widget.update_entries();
+
+        widget
+    }
+
+    fn setup_event_handlers(&self) {
+        let widget = self.clone();
+        self.history.connect_changed(move || {
+            widget.update_entries();
+        });
+
+        let history = self.history.clone();
+        self.clear_button.connect_clicked(move |_| {
+            history.clear();
+        });
+
+        let selected = self.selected.clone();
+        self.list.connect_row_activated(move |_, row| {
+            let index = row.get_index() as u32;
+            selected.activate(Some(&index.to_variant()));
+        });
+    }
+
+    fn update_entries(&self) {
+        self.clear();
+
+        let history = self.history.get_entries();
+        let suggestions = self
+            .history
+            .get_reverse_suggestions(MAX_REVERSE_SUGGESTIONS);
+
+        for entry in &history {
+            self.list.add(&self.create_row(entry, false));
+        }
+
+        for entry in &suggestions {
+            self.list.add(&self.create_row(entry, true));
+        }
+
+        let is_empty = history.is_empty();
+        *self.entries.lock().unwrap() = history.into_iter().chain(suggestions).collect();
+
+        self.list.show_all();
+        self.container.set_visible(!is_empty);
+    }
+
+    fn create_row(&self, entry: &HistoryEntry, is_suggestion: bool) -> gtk::ListBoxRow {
+        let (icon_name, tooltip) = match is_suggestion {
+            true => ("media-playlist-repeat-symbolic", "Return trip"),
+            false => ("document-open-recent-symbolic", "Search again"),
+        };
+
+        let icon = gtk::Image::from_icon_name(Some(icon_name), gtk::IconSize::Menu);
+        icon.set_margin_start(5);
+        icon.set_margin_end(5);
+
+        let label = gtk::Label::new(None);
+        label.set_markup(&Self::get_label_text(entry, is_suggestion));
+        label.set_xalign(0.0);
+        label.set_hexpand(true);
+        label.set_margin_top(5);
+        label.set_margin_bottom(5);
+
+        let row_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        row_box.add(&icon);
+        row_box.add(&label);
+
+        if !is_suggestion {
+            let remove_button =
+                gtk::Button::from_icon_name(Some("window-close-symbolic"), gtk::IconSize::Menu);
+            remove_button.set_relief(gtk::ReliefStyle::None);
+            remove_button.set_tooltip_text(Some("Remove from history"));
+            remove_button.set_valign(gtk::Align::Center);
+            remove_button.set_margin_end(5);
+
+            let history = self.history.clone();
+            let entry = entry.clone();
+            remove_button.connect_clicked(move |_| {
+                history.remove(&entry);
+            });
+
+            row_box.add(&remove_button);
+        }
+
+        let row = gtk::ListBoxRow::new();
+        row.set_tooltip_text(Some(tooltip));
+        row.add(&row_box);
+
+        row
+    }
+
+    fn get_label_text(entry: &HistoryEntry, is_suggestion: bool) -> String {
+        let mut details = vec![];
+        if !entry.vias.is_empty() {
+            details.push(format!("via {}", entry.vias.join(", ")));
+        }
+
+        if !is_suggestion {
+            let searched_at = entry.searched_at.with_timezone(&Local);
+            details.push(searched_at.format("%d.%m. %H:%M").to_string());
+        }
+
+        let title = glib::markup_escape_text(&format!("{} → {}", entry.from, entry.to));
+        match details.is_empty() {
+            true => title.to_string(),
+            false => format!(
+                "{}\n<small>{}</small>",
+                title,
+                glib::markup_escape_text(&details.join(" · "))
+            ),
+        }
+    }
+
+    fn clear(&self) {
+        self.list.foreach(|child| {
+            self.list.remove(child);
+        });
+    }
+
+    pub fn connect_selected<F>(&self, callback: F)
+    where
+        F: Fn(&HistoryEntry) + 'static,
+    {
+        let entries = self.entries.clone();
+        self.selected.connect_activate(move |_, index| {
+            let index = index.and_then(|i| i.get::<u32>()).unwrap() as usize;
+            let entry = entries.lock().unwrap().get(index).cloned();
+
+            if let Some(entry) = entry {
+                callback(&entry);
+            }
+        });
+    }
+}
@@ -1,11 +1,21 @@
+use gio::prelude::*;
 use gtk::prelude::*;
 
-use std::thread;
+use std::sync::{Arc, Mutex};
 
-use crate::api;
+use crate::api::{Coordinate, Location, LocationKind, LocationSearch, LocationSearchSession};
+use crate::format;
+
+// wait for a pause in typing instead of querying every keystroke
+const DEBOUNCE_INTERVAL_MS: u32 = 250;
+const MIN_QUERY_LENGTH: usize = 2;
+
+const COLUMN_MARKUP: u32 = 0;
+const COLUMN_ICON: u32 = 1;
+const COLUMN_INDEX: u32 = 2;
 
 enum LocationEntryMessage {
-    UpdateAutoCompleteList(Vec<String>),
+    UpdateAutoCompleteList(u64, Vec<Location>),
 }
 
 #[derive(Clone)]
@@ -13,15 +23,31 @@ pub struct LocationEntry {
     pub container: gtk::Entry,
     sender: glib::Sender<LocationEntryMessage>,
     completion: gtk::EntryCompletion,
+    session: LocationSearchSession,
+    pending_query: Arc<Mutex<Option<glib::SourceId>>>,
+    locations: Arc<Mutex<Vec<Location>>>,
+    selected: Arc<Mutex<Option<Location>>>,
+    reference: Arc<Mutex<Option<Coordinate>>>,
+    location_selected: gio::SimpleAction,
 }
 
 impl LocationEntry {
-    pub fn new() -> Self {
+    pub fn new(location_search: LocationSearch) -> Self {
         let completion = gtk::EntryCompletion::new();
-        completion.set_text_column(0);
-        completion.set_minimum_key_length(2);
+        completion.set_minimum_key_length(MIN_QUERY_LENGTH as i32);
         completion.set_popup_completion(true);
 
+        // the list only contains matches of the current text, which need not start with it
+        completion.set_match_func(|_, _, _| true);
+
+        let icon = gtk::CellRendererPixbuf::new();
+        completion.pack_start(&icon, false);
+        completion.add_attribute(&icon, "icon-name", COLUMN_ICON as i32);
+
+        let text = gtk::CellRendererText::new();
+        completion.pack_start(&text, true);
+        completion.add_attribute(&text, "markup", COLUMN_MARKUP as i32);
+
         let container = gtk::Entry::new();
         container.set_margin_top(5);
         container.set_margin_bottom(5);
@@ -36,6 +62,12 @@ impl LocationEntry {
             container,
             sender,
             completion,
+            session: location_search.create_session(),
+            pending_query: Arc::new(Mutex::new(None)),
+            locations: Arc::new(Mutex::new(vec![])),
+            selected: Arc::new(Mutex::new(None)),
+            reference: Arc::new(Mutex::new(None)),
+            location_selected: gio::SimpleAction::new("location-selected", None),
         };
 
         widget.setup_event_handlers(receiver);
@@ -47,8 +79,11 @@ impl LocationEntry {
         let parent = self.clone();
         receiver.attach(None, move |msg| {
             match msg {
-                LocationEntryMessage::UpdateAutoCompleteList(locations) => {
-                    parent.set_auto_complete_list(locations);
+                LocationEntryMessage::UpdateAutoCompleteList(sequence, locations) => {
+                    // a slower response to an older text must not replace newer results
+                    if parent.session.is_current(sequence) {
+                        parent.set_auto_complete_list(locations);
+                    }
                 }
             }
 
@@ -56,35 +91,160 @@ impl LocationEntry {
             glib::Continue(true)
         });
 
+        let parent = self.clone();
+        self.completion
+            .connect_match_selected(move |_, model, iter| {
+                let index = model.get_value(iter, COLUMN_INDEX as i32).get_some::<u32>();
+                let location = index
+                    .ok()
+                    .and_then(|i| parent.locations.lock().unwrap().get(i as usize).cloned());
+
+                if let Some(location) = location {
+                    parent.set_location(&location);
+                }
+
+                Inhibit(true)
+            });
+
         let parent = self.clone();
         self.connect_changed(move || {
+            if !parent.is_selected_text() && parent.selected.lock().unwrap().take().is_some() {
+                parent.location_selected.activate(None);
+            }
+
             parent.update_completion_list();
         });
     }
 
+    fn is_selected_text(&self) -> bool {
+        match &*self.selected.lock().unwrap() {
+            Some(location) => location.name == self.get_text(),
+            None => false,
+        }
+    }
+
     fn update_completion_list(&self) {
+        if let Some(source) = self.pending_query.lock().unwrap().take() {
+            glib::source_remove(source);
+        }
+
         let text = self.get_text();
-        let sender = self.sender.clone();
+        let sequence = self.session.next_sequence();
+        // there is nothing left to complete once a location was picked
+        if text.trim().chars().count() < MIN_QUERY_LENGTH || self.is_selected_text() {
+            return;
+        }
 
-        thread::spawn(move || {
-            if let Ok(locations) = api::search_location(&text) {
-                let _ = sender.send(LocationEntryMessage::UpdateAutoCompleteList(locations));
-            }
+        if let Some(locations) = self.session.get_cached(&text) {
+            self.set_auto_complete_list(locations);
+            return;
+        }
+
+        // show the stations starting with the text right away, the network results
+        // and the stations with a typo are added later
+        let offline = self.session.search_offline(&text);
+        if !offline.is_empty() {
+            self.set_auto_complete_list(offline);
+        }
+
+        let parent = self.clone();
+        let source = glib::timeout_add_local(DEBOUNCE_INTERVAL_MS, move || {
+            // the source is removed by returning false
+            parent.pending_query.lock().unwrap().take();
+
+            let sender = parent.sender.clone();
+            parent
+                .session
+                .search(&text, sequence, move |sequence, locations| {
+                    let _ = sender.send(LocationEntryMessage::UpdateAutoCompleteList(
+                        sequence, locations,
+                    ));
+                });
+
+            glib::Continue(false)
         });
+
+        *self.pending_query.lock().unwrap() = Some(source);
     }
 
-    fn set_auto_complete_list(&self, locations: Vec<String>) {
-        let store = gtk::ListStore::new(&[String::static_type()]);
-        let col_indices: [u32; 1] = [0];
+    fn set_auto_complete_list(&self, locations: Vec<Location>) {
+        let store = gtk::ListStore::new(&[
+            String::static_type(),
+            String::static_type(),
+            u32::static_type(),
+        ]);
+        let col_indices: [u32; 3] = [COLUMN_MARKUP, COLUMN_ICON, COLUMN_INDEX];
 
-        for location in locations.iter() {
-            let values: [&dyn ToValue; 1] = [&location];
+        for (index, location) in locations.iter().enumerate() {
+            let markup = self.get_markup(location);
+            let icon = Self::get_icon_name(location);
+            let values: [&dyn ToValue; 3] = [&markup, &icon, &(index as u32)];
             store.set(&store.append(), &col_indices, &values);
         }
 
+        *self.locations.lock().unwrap() = locations;
         self.completion.set_model(Some(&store));
     }
 
+    /// The name with the locality and distance below, to tell apart stops of the same name.
+    fn get_markup(&self, location: &Location) -> String {
+        let mut details = vec![];
+        if let Some(locality) = location.locality() {
+            details.push(glib::markup_escape_text(locality).to_string());
+        }
+
+        let distance = match (&*self.reference.lock().unwrap(), &location.coordinate) {
+            (Some(reference), Some(coordinate)) => reference.distance_to(coordinate),
+            _ => None,
+        };
+        if let Some(distance) = distance {
+            details.push(format::format_distance(distance));
+        }
+
+        let name = glib::markup_escape_text(location.short_name());
+        match details.is_empty() {
+            true => name.to_string(),
+            false => format!("{}\n<small>{}</small>", name, details.join(" · ")),
+        }
+    }
+
+    fn get_icon_name(location: &Location) -> &'static str {
+        match location.kind() {
+            LocationKind::Station => "mark-location-symbolic",
+            LocationKind::Address => "user-home-symbolic",
+            LocationKind::PointOfInterest => "find-location-symbolic",
+        }
+    }
+
+    /// The location picked from the list, as long as the text was not edited since.
+    pub fn get_location(&self) -> Option<Location> {
+        match self.is_selected_text() {
+            true => self.selected.lock().unwrap().clone(),
+            false => None,
+        }
+    }
+
+    pub fn set_location(&self, location: &Location) {
+        // the text is set afterwards so the change is known to match the selection
+        *self.selected.lock().unwrap() = Some(location.clone());
+        self.set_text(&location.name);
+        self.location_selected.activate(None);
+    }
+
+    /// The position the distances of the suggestions are measured from.
+    pub fn set_reference_coordinate(&self, coordinate: Option<Coordinate>) {
+        *self.reference.lock().unwrap() = coordinate;
+    }
+
+    pub fn connect_location_selected<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.location_selected.connect_activate(move |_, _| {
+            callback();
+        });
+    }
+
     pub fn connect_changed<F>(&self, callback: F)
     where
         F: Fn() + 'static,
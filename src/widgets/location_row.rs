@@ -3,6 +3,7 @@ use gtk::prelude::*;
 
 use std::sync::Arc;
 
+use crate::api::{Coordinate, Location, LocationSearch};
 use crate::favorites::Favorites;
 use crate::string_event_handler::StringEventHandler;
 use crate::widgets::LocationEntry;
@@ -24,6 +25,7 @@ impl LocationRowWidget {
         caption: &str,
         label_size_group: &gtk::SizeGroup,
         favorites: Arc<Favorites>,
+        location_search: LocationSearch,
     ) -> Self {
         let label_caption = format!("{}:", caption);
         let label = gtk::Label::new(Some(&label_caption));
@@ -32,7 +34,7 @@ impl LocationRowWidget {
         label.set_margin_start(5);
         label.set_margin_end(0);
 
-        let entry = LocationEntry::new();
+        let entry = LocationEntry::new(location_search);
 
         let favorite_button = gtk::Button::new();
         favorite_button.set_margin_top(5);
@@ -120,6 +122,23 @@ impl LocationRowWidget {
         self.entry.set_text(text);
     }
 
+    pub fn get_location(&self) -> Option<Location> {
+        self.entry.get_location()
+    }
+
+    /// Fills in the name and queries by the id of the location.
+    pub fn set_location(&self, location: &Location) {
+        self.entry.set_location(location);
+    }
+
+    pub fn set_reference_coordinate(&self, coordinate: Option<Coordinate>) {
+        self.entry.set_reference_coordinate(coordinate);
+    }
+
+    pub fn grab_focus(&self) {
+        self.entry.container.grab_focus();
+    }
+
     fn is_current_text_in_favorites(&self) -> bool {
         self.favorites.contains(&self.get_text())
     }
@@ -162,6 +181,13 @@ impl LocationRowWidget {
         });
     }
 
+    pub fn connect_location_selected<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.entry.connect_location_selected(callback);
+    }
+
     pub fn connect_changed<F>(&self, callback: F)
     where
         F: Fn() + 'static,
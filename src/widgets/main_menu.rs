@@ -0,0 +1,356 @@
+use gio::prelude::*;
+use gtk::prelude::*;
+
+use std::path;
+use std::sync::Arc;
+use std::thread;
+
+use crate::api::LocationSearch;
+use crate::favorites::{Favorites, ImportMode, ImportReport};
+use crate::stations::{StationDirectory, StationDirectoryStore};
+
+const EXPORT_FILE_NAME: &str = "sbb-favorites.json";
+const RESPONSE_MERGE: u16 = 1;
+const RESPONSE_REPLACE: u16 = 2;
+
+enum MainMenuMessage {
+    DirectoryLoaded(Result<Option<StationDirectory>, String>),
+    DirectoryImported(Result<StationDirectory, String>),
+}
+
+#[derive(Clone)]
+pub struct MainMenuWidget {
+    pub container: gtk::MenuButton,
+    favorites: Arc<Favorites>,
+    location_search: LocationSearch,
+    station_store: StationDirectoryStore,
+    remove_directory: gio::SimpleAction,
+    nearby_stations: gio::SimpleAction,
+    sender: glib::Sender<MainMenuMessage>,
+}
+
+impl MainMenuWidget {
+    pub fn new(favorites: Arc<Favorites>, location_search: LocationSearch) -> Self {
+        let favorites_section = gio::Menu::new();
+        favorites_section.append(Some("Import favorites…"), Some("menu.import-favorites"));
+        favorites_section.append(Some("Export favorites…"), Some("menu.export-favorites"));
+
+        let stations_section = gio::Menu::new();
+        stations_section.append(
+            Some("Import station directory…"),
+            Some("menu.import-station-directory"),
+        );
+        stations_section.append(
+            Some("Remove station directory"),
+            Some("menu.remove-station-directory"),
+        );
+
+        let search_section = gio::Menu::new();
+        search_section.append(Some("Stations near…"), Some("menu.nearby-stations"));
+
+        let menu = gio::Menu::new();
+        menu.append_section(None, &search_section);
+        menu.append_section(None, &favorites_section);
+        menu.append_section(None, &stations_section);
+
+        let container = gtk::MenuButtonBuilder::new()
+            .tooltip_text("Menu")
+            .menu_model(&menu)
+            .build();
+
+        let icon = gtk::Image::from_icon_name(Some("open-menu-symbolic"), gtk::IconSize::Menu);
+        container.set_image(Some(&icon));
+
+        let remove_directory = gio::SimpleAction::new("remove-station-directory", None);
+        remove_directory.set_enabled(false);
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        let widget = Self {
+            container,
+            favorites,
+            location_search,
+            station_store: StationDirectoryStore::new(),
+            remove_directory,
+            nearby_stations: gio::SimpleAction::new("nearby-stations", None),
+            sender,
+        };
+
+        widget.setup_event_handlers(receiver);
+        widget.load_station_directory();
+
+        widget
+    }
+
+    fn setup_event_handlers(&self, receiver: glib::Receiver<MainMenuMessage>) {
+        let actions = gio::SimpleActionGroup::new();
+
+        let import = gio::SimpleAction::new("import-favorites", None);
+        let widget = self.clone();
+        import.connect_activate(move |_, _| {
+            widget.import_favorites();
+        });
+        actions.add_action(&import);
+
+        let export = gio::SimpleAction::new("export-favorites", None);
+        let widget = self.clone();
+        export.connect_activate(move |_, _| {
+            widget.export_favorites();
+        });
+        actions.add_action(&export);
+
+        let import_directory = gio::SimpleAction::new("import-station-directory", None);
+        let widget = self.clone();
+        import_directory.connect_activate(move |_, _| {
+            widget.import_station_directory();
+        });
+        actions.add_action(&import_directory);
+
+        let widget = self.clone();
+        self.remove_directory.connect_activate(move |_, _| {
+            widget.remove_station_directory();
+        });
+        actions.add_action(&self.remove_directory);
+
+        actions.add_action(&self.nearby_stations);
+
+        self.container.insert_action_group("menu", Some(&actions));
+
+        let widget = self.clone();
+        receiver.attach(None, move |msg| {
+            match msg {
+                MainMenuMessage::DirectoryLoaded(Ok(directory)) => {
+                    widget.set_station_directory(directory);
+                }
+                MainMenuMessage::DirectoryLoaded(Err(error)) => widget.show_message(
+                    gtk::MessageType::Error,
+                    "Failed to load the station directory",
+                    &format!("{}\n\nPlease import the directory again.", error),
+                ),
+                MainMenuMessage::DirectoryImported(Ok(directory)) => {
+                    let text = format!("{} stations are available offline.", directory.len());
+                    widget.set_station_directory(Some(directory));
+                    widget.show_message(
+                        gtk::MessageType::Info,
+                        "Station directory imported",
+                        &text,
+                    );
+                }
+                MainMenuMessage::DirectoryImported(Err(error)) => widget.show_message(
+                    gtk::MessageType::Error,
+                    "Failed to import the station directory",
+                    &error,
+                ),
+            }
+
+            glib::Continue(true)
+        });
+    }
+
+    fn get_window(&self) -> Option<gtk::Window> {
+        self.container
+            .get_toplevel()
+            .and_then(|w| w.downcast::<gtk::Window>().ok())
+    }
+
+    fn choose_file(
+        &self,
+        title: &str,
+        action: gtk::FileChooserAction,
+        filter: &gtk::FileFilter,
+    ) -> Option<path::PathBuf> {
+        let accept_label = match action {
+            gtk::FileChooserAction::Save => "_Save",
+            _ => "_Open",
+        };
+
+        let window = self.get_window();
+        let dialog = gtk::FileChooserNative::new(
+            Some(title),
+            window.as_ref(),
+            action,
+            Some(accept_label),
+            Some("_Cancel"),
+        );
+
+        if action == gtk::FileChooserAction::Save {
+            dialog.set_current_name(EXPORT_FILE_NAME);
+            dialog.set_do_overwrite_confirmation(true);
+        }
+
+        dialog.add_filter(filter);
+
+        match dialog.run() {
+            gtk::ResponseType::Accept => dialog.get_filename(),
+            _ => None,
+        }
+    }
+
+    fn create_favorites_filter() -> gtk::FileFilter {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("Favorites"));
+        filter.add_mime_type("application/json");
+        filter.add_pattern("*.json");
+        filter
+    }
+
+    fn export_favorites(&self) {
+        let filter = Self::create_favorites_filter();
+        let path = match self.choose_file("Export favorites", gtk::FileChooserAction::Save, &filter)
+        {
+            Some(path) => path,
+            None => return,
+        };
+
+        if let Err(error) = self.favorites.export(&path) {
+            self.show_message(
+                gtk::MessageType::Error,
+                "Failed to export the favorites",
+                &error,
+            );
+        }
+    }
+
+    fn import_favorites(&self) {
+        let filter = Self::create_favorites_filter();
+        let path = match self.choose_file("Import favorites", gtk::FileChooserAction::Open, &filter)
+        {
+            Some(path) => path,
+            None => return,
+        };
+
+        let mode = match self.ask_import_mode() {
+            Some(mode) => mode,
+            None => return,
+        };
+
+        match self.favorites.import(&path, mode) {
+            Ok(report) => self.show_message(
+                gtk::MessageType::Info,
+                "Favorites imported",
+                &Self::get_report_text(&report),
+            ),
+            Err(error) => self.show_message(
+                gtk::MessageType::Error,
+                "Failed to import the favorites",
+                &error,
+            ),
+        }
+    }
+
+    fn ask_import_mode(&self) -> Option<ImportMode> {
+        let dialog = gtk::MessageDialogBuilder::new()
+            .modal(true)
+            .message_type(gtk::MessageType::Question)
+            .text("Import favorites")
+            .secondary_text(
+                "Merge the imported favorites with the existing ones or replace all existing favorites?",
+            )
+            .build();
+        dialog.set_transient_for(self.get_window().as_ref());
+        dialog.add_button("_Cancel", gtk::ResponseType::Cancel);
+        dialog.add_button("_Replace", gtk::ResponseType::Other(RESPONSE_REPLACE));
+        dialog.add_button("_Merge", gtk::ResponseType::Other(RESPONSE_MERGE));
+        dialog.set_default_response(gtk::ResponseType::Other(RESPONSE_MERGE));
+
+        let response = dialog.run();
+        unsafe {
+            dialog.destroy();
+        }
+
+        match response {
+            gtk::ResponseType::Other(RESPONSE_MERGE) => Some(ImportMode::Merge),
+            gtk::ResponseType::Other(RESPONSE_REPLACE) => Some(ImportMode::Replace),
+            _ => None,
+        }
+    }
+
+    fn get_report_text(report: &ImportReport) -> String {
+        let mut text = format!(
+            "{} stations and {} routes were added.",
+            report.stations_added, report.routes_added
+        );
+
+        if !report.conflicts.is_empty() {
+            text.push_str("\n\nThe existing favorites were kept for these conflicts:");
+            for conflict in &report.conflicts {
+                text.push_str(&format!("\n• {}", conflict));
+            }
+        }
+
+        text
+    }
+
+    fn load_station_directory(&self) {
+        let store = self.station_store.clone();
+        let sender = self.sender.clone();
+
+        thread::spawn(move || {
+            let _ = sender.send(MainMenuMessage::DirectoryLoaded(store.load()));
+        });
+    }
+
+    fn import_station_directory(&self) {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("GTFS stops.txt or service points CSV"));
+        filter.add_mime_type("text/csv");
+        filter.add_pattern("*.csv");
+        filter.add_pattern("*.txt");
+
+        let path = match self.choose_file(
+            "Import station directory",
+            gtk::FileChooserAction::Open,
+            &filter,
+        ) {
+            Some(path) => path,
+            None => return,
+        };
+
+        // large directories take a few seconds to index
+        let store = self.station_store.clone();
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let _ = sender.send(MainMenuMessage::DirectoryImported(store.import(&path)));
+        });
+    }
+
+    fn remove_station_directory(&self) {
+        match self.station_store.remove() {
+            Ok(()) => self.set_station_directory(None),
+            Err(error) => self.show_message(
+                gtk::MessageType::Error,
+                "Failed to remove the station directory",
+                &error,
+            ),
+        }
+    }
+
+    fn set_station_directory(&self, directory: Option<StationDirectory>) {
+        self.remove_directory.set_enabled(directory.is_some());
+        self.location_search.set_directory(directory);
+    }
+
+    fn show_message(&self, message_type: gtk::MessageType, text: &str, secondary_text: &str) {
+        let dialog = gtk::MessageDialogBuilder::new()
+            .modal(true)
+            .message_type(message_type)
+            .buttons(gtk::ButtonsType::Close)
+            .text(text)
+            .secondary_text(secondary_text)
+            .build();
+        dialog.set_transient_for(self.get_window().as_ref());
+        dialog.connect_response(|d, _| unsafe {
+            d.destroy();
+        });
+        dialog.show_all();
+    }
+
+    pub fn connect_nearby_stations<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.nearby_stations.connect_activate(move |_, _| {
+            callback();
+        });
+    }
+}
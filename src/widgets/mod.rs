@@ -1,20 +1,37 @@
 mod connection;
+mod connection_detail;
 mod connection_list;
 mod date_time_picker_popover;
 mod favorite_box;
+mod history_box;
 mod location_entry;
 mod location_row;
+mod main_menu;
+mod nearby_stations;
+mod prompt;
+mod route_box;
 mod search;
 mod section;
+mod stationboard;
+mod stationboard_entry;
 mod time_row;
+mod transport_filter;
 mod via_box;
 
 pub use self::connection::ConnectionWidget;
+pub use self::connection_detail::ConnectionDetailWidget;
 pub use self::connection_list::ConnectionListWidget;
 pub use self::favorite_box::FavoriteBoxWidget;
+pub use self::history_box::HistoryBoxWidget;
 pub use self::location_entry::LocationEntry;
 pub use self::location_row::LocationRowWidget;
+pub use self::main_menu::MainMenuWidget;
+pub use self::nearby_stations::NearbyStationsWidget;
+pub use self::route_box::RouteBoxWidget;
 pub use self::search::SearchWidget;
 pub use self::section::SectionWidget;
+pub use self::stationboard::StationboardWidget;
+pub use self::stationboard_entry::StationboardEntryWidget;
 pub use self::time_row::TimeRowWidget;
+pub use self::transport_filter::TransportFilterWidget;
 pub use self::via_box::ViaBoxWidget;
@@ -0,0 +1,284 @@
+use gio::prelude::*;
+use gtk::prelude::*;
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::api::{Error, Location, TimetableBackend};
+use crate::format;
+use crate::geo;
+
+enum NearbyStationsMessage {
+    Update(u64, Result<Vec<Location>, Error>),
+}
+
+/// A dialog listing the stations closest to typed or pasted coordinates.
+#[derive(Clone)]
+pub struct NearbyStationsWidget {
+    pub container: gtk::Dialog,
+    entry: gtk::Entry,
+    search_button: gtk::Button,
+    status_label: gtk::Label,
+    list: gtk::ListBox,
+    backend: Arc<dyn TimetableBackend>,
+    sender: glib::Sender<NearbyStationsMessage>,
+    // every search gets a new id, results of an older search are dropped
+    current_search: Arc<Mutex<u64>>,
+    stations: Arc<Mutex<Vec<Location>>>,
+    use_as_from: gio::SimpleAction,
+    use_as_to: gio::SimpleAction,
+}
+
+impl NearbyStationsWidget {
+    pub fn new<W: IsA<gtk::Window>>(parent: &W, backend: Arc<dyn TimetableBackend>) -> Self {
+        let container = gtk::Dialog::with_buttons(
+            Some("Stations near…"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT,
+            &[("_Close", gtk::ResponseType::Close)],
+        );
+        container.set_default_size(360, 420);
+
+        let entry = gtk::EntryBuilder::new()
+            .placeholder_text("47.5474, 7.5895 or a map link")
+            .tooltip_text("WGS84 degrees, Swiss LV95 coordinates, a geo: URI or a map URL")
+            .hexpand(true)
+            .build();
+
+        let search_button =
+            gtk::Button::from_icon_name(Some("edit-find-symbolic"), gtk::IconSize::Menu);
+        search_button.set_tooltip_text(Some("Find stations"));
+
+        let search_box = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+        search_box.set_margin_top(5);
+        search_box.set_margin_bottom(5);
+        search_box.set_margin_start(5);
+        search_box.set_margin_end(5);
+        search_box.add(&entry);
+        search_box.add(&search_button);
+
+        let status_label = gtk::Label::new(None);
+        status_label.set_line_wrap(true);
+        status_label.set_xalign(0.0);
+        status_label.set_margin_start(5);
+        status_label.set_margin_end(5);
+        status_label.get_style_context().add_class("dim-label");
+
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::None);
+
+        let hadjust: Option<&gtk::Adjustment> = None;
+        let vadjust: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(hadjust, vadjust);
+        scrolled_window.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled_window.set_vexpand(true);
+        scrolled_window.add(&list);
+
+        let content = container.get_content_area();
+        content.add(&search_box);
+        content.add(&status_label);
+        content.add(&scrolled_window);
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        let widget = Self {
+            container,
+            entry,
+            search_button,
+            status_label,
+            list,
+            backend,
+            sender,
+            current_search: Arc::new(Mutex::new(0)),
+            stations: Arc::new(Mutex::new(vec![])),
+            use_as_from: gio::SimpleAction::new("use-as-from", Some(0u32.to_variant().type_())),
+            use_as_to: gio::SimpleAction::new("use-as-to", Some(0u32.to_variant().type_())),
+        };
+
+        widget.setup_event_handlers(receiver);
+
+        widget
+    }
+
+    fn setup_event_handlers(&self, receiver: glib::Receiver<NearbyStationsMessage>) {
+        let widget = self.clone();
+        receiver.attach(None, move |msg| {
+            match msg {
+                NearbyStationsMessage::Update(id, result) => {
+                    if id == *widget.current_search.lock().unwrap() {
+                        widget.set_result(result);
+                    }
+                }
+            }
+
+            glib::Continue(true)
+        });
+
+        let widget = self.clone();
+        self.entry.connect_activate(move |_| {
+            widget.search();
+        });
+
+        let widget = self.clone();
+        self.search_button.connect_clicked(move |_| {
+            widget.search();
+        });
+
+        self.container.connect_response(|dialog, _| unsafe {
+            dialog.destroy();
+        });
+    }
+
+    pub fn show(&self) {
+        self.container.show_all();
+        self.status_label.set_visible(false);
+    }
+
+    fn search(&self) {
+        let text = self.entry.get_text().to_string();
+        let position = geo::parse_coordinate(&text).map(|c| c.latitude_longitude());
+
+        let (latitude, longitude) = match position {
+            Ok(Some(position)) => position,
+            Ok(None) => return,
+            Err(error) => {
+                self.set_status(Some(&error));
+                return;
+            }
+        };
+
+        let id = {
+            let mut current_search = self.current_search.lock().unwrap();
+            *current_search += 1;
+            *current_search
+        };
+
+        self.set_stations(vec![]);
+        self.set_status(Some("Searching…"));
+
+        let backend = self.backend.clone();
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let result = backend.search_nearby(latitude, longitude);
+            let _ = sender.send(NearbyStationsMessage::Update(id, result));
+        });
+    }
+
+    fn set_result(&self, result: Result<Vec<Location>, Error>) {
+        match result {
+            Ok(stations) if stations.is_empty() => {
+                self.set_status(Some("No stations were found near this place."));
+            }
+            Ok(stations) => {
+                self.set_status(None);
+                self.set_stations(stations);
+            }
+            Err(error) => self.set_status(Some(&error.to_string())),
+        }
+    }
+
+    fn set_status(&self, text: Option<&str>) {
+        self.status_label.set_text(text.unwrap_or(""));
+        self.status_label.set_visible(text.is_some());
+    }
+
+    fn set_stations(&self, stations: Vec<Location>) {
+        self.list.foreach(|child| {
+            self.list.remove(child);
+        });
+
+        for (index, station) in stations.iter().enumerate() {
+            self.list.add(&self.create_row(index as u32, station));
+        }
+
+        *self.stations.lock().unwrap() = stations;
+        self.list.show_all();
+    }
+
+    fn create_row(&self, index: u32, station: &Location) -> gtk::ListBoxRow {
+        let icon = gtk::Image::from_icon_name(Some("mark-location-symbolic"), gtk::IconSize::Menu);
+        icon.set_margin_start(5);
+        icon.set_margin_end(5);
+
+        let label = gtk::Label::new(None);
+        label.set_markup(&Self::get_label_text(station));
+        label.set_xalign(0.0);
+        label.set_hexpand(true);
+        label.set_line_wrap(true);
+        label.set_margin_top(5);
+        label.set_margin_bottom(5);
+
+        let from_button = gtk::Button::with_label("From");
+        from_button.set_tooltip_text(Some("Use as departure station"));
+        from_button.set_valign(gtk::Align::Center);
+
+        let to_button = gtk::Button::with_label("To");
+        to_button.set_tooltip_text(Some("Use as arrival station"));
+        to_button.set_valign(gtk::Align::Center);
+        to_button.set_margin_start(5);
+        to_button.set_margin_end(5);
+
+        let use_as_from = self.use_as_from.clone();
+        from_button.connect_clicked(move |_| {
+            use_as_from.activate(Some(&index.to_variant()));
+        });
+
+        let use_as_to = self.use_as_to.clone();
+        to_button.connect_clicked(move |_| {
+            use_as_to.activate(Some(&index.to_variant()));
+        });
+
+        let row_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        row_box.add(&icon);
+        row_box.add(&label);
+        row_box.add(&from_button);
+        row_box.add(&to_button);
+
+        let row = gtk::ListBoxRow::new();
+        row.add(&row_box);
+
+        row
+    }
+
+    fn get_label_text(station: &Location) -> String {
+        let name = glib::markup_escape_text(&station.name);
+
+        match station.distance {
+            Some(distance) => format!(
+                "{}\n<small>{}</small>",
+                name,
+                format::format_distance(distance)
+            ),
+            None => name.to_string(),
+        }
+    }
+
+    fn connect_station_action<F>(&self, action: &gio::SimpleAction, callback: F)
+    where
+        F: Fn(&Location) + 'static,
+    {
+        let stations = self.stations.clone();
+        action.connect_activate(move |_, index| {
+            let index = index.and_then(|i| i.get::<u32>()).unwrap() as usize;
+            let station = stations.lock().unwrap().get(index).cloned();
+
+            if let Some(station) = station {
+                callback(&station);
+            }
+        });
+    }
+
+    pub fn connect_use_as_from<F>(&self, callback: F)
+    where
+        F: Fn(&Location) + 'static,
+    {
+        self.connect_station_action(&self.use_as_from, callback);
+    }
+
+    pub fn connect_use_as_to<F>(&self, callback: F)
+    where
+        F: Fn(&Location) + 'static,
+    {
+        self.connect_station_action(&self.use_as_to, callback);
+    }
+}
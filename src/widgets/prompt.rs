@@ -0,0 +1,48 @@
+use gtk::prelude::*;
+
+/// Asks for a single line of text in a modal dialog on top of the window of `widget`.
+///
+/// Returns the trimmed text, or `None` if the dialog was cancelled.
+pub(crate) fn ask_text<W: IsA<gtk::Widget>>(
+    widget: &W,
+    title: &str,
+    accept_label: &str,
+    text: &str,
+    placeholder: Option<&str>,
+) -> Option<String> {
+    let window = widget
+        .get_toplevel()
+        .and_then(|w| w.downcast::<gtk::Window>().ok());
+
+    let dialog = gtk::Dialog::with_buttons(
+        Some(title),
+        window.as_ref(),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("_Cancel", gtk::ResponseType::Cancel),
+            (accept_label, gtk::ResponseType::Accept),
+        ],
+    );
+    dialog.set_default_response(gtk::ResponseType::Accept);
+
+    let entry = gtk::EntryBuilder::new()
+        .text(text)
+        .activates_default(true)
+        .margin(5)
+        .build();
+    entry.set_placeholder_text(placeholder);
+    dialog.get_content_area().add(&entry);
+    dialog.show_all();
+
+    let response = dialog.run();
+    let text = entry.get_text().trim().to_owned();
+
+    unsafe {
+        dialog.destroy();
+    }
+
+    match response {
+        gtk::ResponseType::Accept => Some(text),
+        _ => None,
+    }
+}
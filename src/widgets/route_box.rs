@@ -0,0 +1,122 @@
+use gtk::prelude::*;
+
+use std::sync::Arc;
+
+use crate::favorites::{FavoriteRoute, Favorites};
+use crate::string_event_handler::StringEventHandler;
+
+#[derive(Clone)]
+pub struct RouteBoxWidget {
+    pub container: gtk::FlowBox,
+    route_selected: StringEventHandler,
+    favorites: Arc<Favorites>,
+}
+
+impl RouteBoxWidget {
+    pub fn new(favorites: Arc<Favorites>) -> Self {
+        let container = gtk::FlowBox::new();
+        container.set_selection_mode(gtk::SelectionMode::None);
+        container.set_hexpand(true);
+
+        let route_selected = StringEventHandler::new("route-selected");
+
+        let widget = Self {
+            container,
+            favorites,
+            route_selected,
+        };
+
+        widget.setup_event_handlers();
+        widget.update_routes();
+
+        widget
+    }
+
+    fn setup_event_handlers(&self) {
+        let widget = self.clone();
+        self.favorites.connect_changed(move || {
+            widget.update_routes();
+        });
+    }
+
+    fn update_routes(&self) {
+        self.clear();
+
+        for route in self.favorites.get_routes() {
+            let button = gtk::Button::with_label(&route.name);
+            button.set_tooltip_text(Some(&Self::get_route_description(&route)));
+            self.enable_context_menu_on_button(&button, &route.name);
+
+            self.container.add(&button);
+
+            let action = self.route_selected.clone();
+            button.connect_clicked(move |_| {
+                action.trigger(&route.name);
+            });
+        }
+
+        self.container.show_all();
+    }
+
+    fn enable_context_menu_on_button(&self, button: &gtk::Button, name: &str) {
+        let favorites = self.favorites.clone();
+        let name = name.to_owned();
+        button.connect_button_press_event(move |button, event| {
+            if event.get_button() != 3 {
+                return Inhibit(false);
+            }
+
+            let remove_item = gtk::MenuItem::with_label("Remove route");
+            let favorites = favorites.clone();
+            let name = name.clone();
+            remove_item.connect_activate(move |_| {
+                favorites.remove_route(&name);
+            });
+
+            let menu = gtk::Menu::new();
+            menu.set_attach_widget(Some(button));
+            menu.append(&remove_item);
+            menu.show_all();
+            menu.popup_at_pointer(Some(event));
+
+            Inhibit(true)
+        });
+    }
+
+    fn get_route_description(route: &FavoriteRoute) -> String {
+        let mut stations = vec![route.from.clone()];
+        stations.extend(route.vias.iter().cloned());
+        stations.push(route.to.clone());
+
+        let mode = match route.is_arrival_time {
+            true => "Arrival",
+            false => "Departure",
+        };
+
+        let time = match &route.time {
+            Some(time) => format!("{} at {}", mode, time),
+            None => format!("{} now", mode),
+        };
+
+        format!("{}\n{}", stations.join(" - "), time)
+    }
+
+    fn clear(&self) {
+        self.container.foreach(|child| {
+            self.container.remove(child);
+        });
+    }
+
+    pub fn connect_selected<F>(&self, callback: F)
+    where
+        F: Fn(&FavoriteRoute) + 'static,
+    {
+        let favorites = self.favorites.clone();
+        self.route_selected.connect(move |name| {
+            let routes = favorites.get_routes();
+            if let Some(route) = routes.iter().find(|r| r.name == name) {
+                callback(route);
+            }
+        });
+    }
+}
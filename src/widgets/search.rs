@@ -1,39 +1,128 @@
 use gtk::prelude::*;
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
-use crate::api::SearchConnectionRequest;
-use crate::favorites::Favorites;
+use crate::api::{Location, LocationSearch, SearchConnectionRequest, Transportation};
+use crate::favorites::{FavoriteRoute, Favorites};
+use crate::history::{HistoryEntry, SearchHistory};
+use crate::settings::Settings;
+use crate::widgets::prompt;
 use crate::widgets::*;
 
+/// Stores the filters picked by the user as the defaults of the next start.
+#[derive(Clone)]
+struct FilterDefaults {
+    settings: Arc<Settings>,
+    is_restoring: Arc<AtomicBool>,
+}
+
+impl FilterDefaults {
+    fn new(settings: Arc<Settings>) -> Self {
+        Self {
+            settings,
+            is_restoring: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn set_transportations(&self, transportations: &[Transportation]) {
+        if !self.is_restoring.load(Ordering::SeqCst) {
+            self.settings.set_transportations(transportations);
+        }
+    }
+
+    fn set_direct_only(&self, direct_only: bool) {
+        if !self.is_restoring.load(Ordering::SeqCst) {
+            self.settings.set_direct_only(direct_only);
+        }
+    }
+
+    /// Runs `restore` without storing the filters it changes.
+    fn restoring<F: FnOnce()>(&self, restore: F) {
+        self.is_restoring.store(true, Ordering::SeqCst);
+        restore();
+        self.is_restoring.store(false, Ordering::SeqCst);
+    }
+}
+
 #[derive(Clone)]
 pub struct SearchWidget {
     pub container: gtk::Box,
     button: gtk::Button,
+    save_route_button: gtk::Button,
     from_entry: LocationRowWidget,
     to_entry: LocationRowWidget,
     via_box: ViaBoxWidget,
     time_input: TimeRowWidget,
+    transport_filter: TransportFilterWidget,
+    direct_button: gtk::CheckButton,
+    favorites: Arc<Favorites>,
+    history: Arc<SearchHistory>,
+    filter_defaults: FilterDefaults,
+    // the ids of the last applied history entry, used while the names are unchanged
+    restored_location_ids: Arc<Mutex<HashMap<String, String>>>,
 }
 
 impl SearchWidget {
-    pub fn new(favorites: Arc<Favorites>) -> Self {
+    pub fn new(
+        favorites: Arc<Favorites>,
+        history: Arc<SearchHistory>,
+        location_search: LocationSearch,
+        settings: Arc<Settings>,
+    ) -> Self {
         let label_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Horizontal);
 
-        let from_entry = LocationRowWidget::new("From", &label_size_group, favorites.clone());
-        let to_entry = LocationRowWidget::new("To", &label_size_group, favorites.clone());
+        let from_entry = LocationRowWidget::new(
+            "From",
+            &label_size_group,
+            favorites.clone(),
+            location_search.clone(),
+        );
+        let to_entry = LocationRowWidget::new(
+            "To",
+            &label_size_group,
+            favorites.clone(),
+            location_search.clone(),
+        );
 
         let button = gtk::Button::with_label("Submit");
+        button.set_hexpand(true);
         button.set_margin_top(5);
         button.set_margin_bottom(5);
         button.set_margin_start(5);
         button.set_margin_end(5);
 
+        let save_route_button =
+            gtk::Button::from_icon_name(Some("bookmark-new-symbolic"), gtk::IconSize::Menu);
+        save_route_button.set_tooltip_text(Some("Save as favorite route"));
+        save_route_button.set_margin_top(5);
+        save_route_button.set_margin_bottom(5);
+        save_route_button.set_margin_end(5);
+
+        let button_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        button_box.add(&button);
+        button_box.add(&save_route_button);
+
+        let route_box = RouteBoxWidget::new(favorites.clone());
         let fav_box = FavoriteBoxWidget::new(favorites.clone());
-        let via_box = ViaBoxWidget::new(&label_size_group, favorites.clone());
+        let via_box = ViaBoxWidget::new(
+            &label_size_group,
+            favorites.clone(),
+            location_search.clone(),
+        );
+        let history_box = HistoryBoxWidget::new(history.clone());
 
         let time_input = TimeRowWidget::new(&label_size_group);
 
+        let transport_filter = TransportFilterWidget::new();
+        transport_filter.set_transportations(&settings.get_transportations());
+
+        let direct_button = gtk::CheckButton::with_label("Direct connections only");
+        direct_button.set_margin_start(5);
+        direct_button.set_margin_end(5);
+        direct_button.set_active(settings.get_direct_only());
+
         {
             let from_entry = from_entry.clone();
             let to_entry = to_entry.clone();
@@ -50,21 +139,228 @@ impl SearchWidget {
         }
 
         let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        container.add(&route_box.container);
         container.add(&fav_box.container);
         container.add(&from_entry.container);
         container.add(&to_entry.container);
         container.add(&via_box.container);
         container.add(&time_input.container);
-        container.add(&button);
+        container.add(&transport_filter.container);
+        container.add(&direct_button);
+        container.add(&button_box);
+        container.add(&history_box.container);
 
-        Self {
+        let widget = Self {
             container,
             button,
+            save_route_button,
             from_entry,
             to_entry,
             via_box,
             time_input,
+            transport_filter,
+            direct_button,
+            favorites,
+            history,
+            filter_defaults: FilterDefaults::new(settings),
+            restored_location_ids: Arc::new(Mutex::new(HashMap::new())),
+        };
+
+        {
+            let widget = widget.clone();
+            route_box.connect_selected(move |route| {
+                widget.apply_route(route);
+            });
+        }
+
+        {
+            let widget = widget.clone();
+            history_box.connect_selected(move |entry| {
+                widget.apply_history_entry(entry);
+                widget.search();
+            });
+        }
+
+        // the time of the last search is most likely over, only its stations are kept
+        if let Some(entry) = widget.history.get_last() {
+            widget.from_entry.set_text(&entry.from);
+            widget.to_entry.set_text(&entry.to);
+            widget.via_box.set_vias(&entry.vias);
+        }
+
+        widget.setup_event_handlers();
+
+        widget
+    }
+
+    fn setup_event_handlers(&self) {
+        let widget = self.clone();
+        self.transport_filter.connect_changed(move || {
+            let transportations = widget.transport_filter.get_transportations();
+            widget.filter_defaults.set_transportations(&transportations);
+        });
+
+        let widget = self.clone();
+        self.direct_button.connect_toggled(move |button| {
+            widget.filter_defaults.set_direct_only(button.get_active());
+        });
+
+        let widget = self.clone();
+        self.save_route_button.connect_clicked(move |_| {
+            widget.save_route();
+        });
+
+        // suggestions show how far they are from the other end of the trip
+        let widget = self.clone();
+        self.from_entry.connect_location_selected(move || {
+            let coordinate = widget.from_entry.get_location().and_then(|l| l.coordinate);
+            widget.to_entry.set_reference_coordinate(coordinate.clone());
+            widget.via_box.set_reference_coordinate(coordinate);
+        });
+
+        let widget = self.clone();
+        self.to_entry.connect_location_selected(move || {
+            let coordinate = widget.to_entry.get_location().and_then(|l| l.coordinate);
+            widget.from_entry.set_reference_coordinate(coordinate);
+        });
+    }
+
+    /// Fills in the fields of the route and starts the search right away.
+    pub fn apply_route(&self, route: &FavoriteRoute) {
+        self.from_entry.set_text(&route.from);
+        self.to_entry.set_text(&route.to);
+        self.via_box.set_vias(&route.vias);
+        self.time_input.set_arrival_time(route.is_arrival_time);
+        self.time_input.set_time(route.time.as_deref());
+        self.search();
+    }
+
+    /// Fills in all fields of a past search.
+    fn apply_history_entry(&self, entry: &HistoryEntry) {
+        self.from_entry.set_text(&entry.from);
+        self.to_entry.set_text(&entry.to);
+        self.via_box.set_vias(&entry.vias);
+        self.time_input.set_arrival_time(entry.is_arrival_time);
+        self.time_input
+            .set_date_time(entry.date.as_deref(), entry.time.as_deref());
+
+        // the filters of a past search are not the defaults for the next one
+        self.filter_defaults.restoring(|| {
+            self.transport_filter
+                .set_transportations(&entry.transportations);
+            self.direct_button.set_active(entry.direct);
+        });
+        *self.restored_location_ids.lock().unwrap() = entry.location_ids.clone();
+    }
+
+    pub fn set_from_location(&self, location: &Location) {
+        self.from_entry.set_location(location);
+    }
+
+    pub fn set_to_location(&self, location: &Location) {
+        self.to_entry.set_location(location);
+    }
+
+    fn save_route(&self) {
+        let from = self.from_entry.get_text();
+        let to = self.to_entry.get_text();
+        if from.is_empty() || to.is_empty() {
+            return;
         }
+
+        let name = match self.ask_route_name(&format!("{} - {}", from, to)) {
+            Some(name) => name,
+            None => return,
+        };
+
+        self.favorites.add_route(FavoriteRoute {
+            name,
+            from,
+            to,
+            vias: self.via_box.get_vias(),
+            is_arrival_time: self.time_input.is_arrival_time(),
+            time: self.time_input.get_time(),
+        });
+    }
+
+    fn ask_route_name(&self, default_name: &str) -> Option<String> {
+        let name = prompt::ask_text(&self.container, "Save route", "_Save", default_name, None)?;
+
+        match name.is_empty() {
+            true => None,
+            false => Some(name),
+        }
+    }
+
+    pub fn search(&self) {
+        self.button.clicked();
+    }
+
+    pub fn set_searching(&self, is_searching: bool) {
+        self.button.set_sensitive(!is_searching);
+    }
+
+    pub fn focus_location(&self, text: &str) {
+        if let Some(row) = self.find_location(text) {
+            row.grab_focus();
+        }
+    }
+
+    pub fn replace_location(&self, text: &str, replacement: &str) {
+        if let Some(row) = self.find_location(text) {
+            row.set_text(replacement);
+        }
+    }
+
+    fn find_location(&self, text: &str) -> Option<LocationRowWidget> {
+        if self.from_entry.get_text() == text {
+            return Some(self.from_entry.clone());
+        }
+
+        if self.to_entry.get_text() == text {
+            return Some(self.to_entry.clone());
+        }
+
+        self.via_box.find_location(text)
+    }
+
+    fn get_request(&self) -> SearchConnectionRequest {
+        SearchConnectionRequest {
+            from: self.from_entry.get_text(),
+            to: self.to_entry.get_text(),
+            page: 0,
+            vias: self.via_box.get_vias(),
+            date: self.time_input.get_date(),
+            time: self.time_input.get_time(),
+            is_arrival_time: self.time_input.is_arrival_time(),
+            transportations: self.transport_filter.get_transportations(),
+            direct: self.direct_button.get_active(),
+            location_ids: self.get_location_ids(),
+        }
+    }
+
+    /// The ids of the locations picked from the suggestions or restored from the
+    /// history, by their name.
+    fn get_location_ids(&self) -> HashMap<String, String> {
+        let mut names = self.via_box.get_vias();
+        names.push(self.from_entry.get_text());
+        names.push(self.to_entry.get_text());
+
+        let mut ids: HashMap<String, String> = self
+            .restored_location_ids
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| names.contains(name))
+            .map(|(name, id)| (name.clone(), id.clone()))
+            .collect();
+
+        let mut locations = self.via_box.get_locations();
+        locations.extend(self.from_entry.get_location());
+        locations.extend(self.to_entry.get_location());
+        ids.extend(locations.into_iter().filter_map(|l| Some((l.name, l.id?))));
+
+        ids
     }
 
     pub fn connect_search<F>(&self, callback: F)
@@ -74,17 +370,43 @@ impl SearchWidget {
         let parent = self.clone();
 
         self.button.connect_clicked(move |_| {
-            let data = SearchConnectionRequest {
-                from: parent.from_entry.get_text(),
-                to: parent.to_entry.get_text(),
-                page: 0,
-                vias: parent.via_box.get_vias(),
-                date: parent.time_input.get_date(),
-                time: parent.time_input.get_time(),
-                is_arrival_time: parent.time_input.is_arrival_time(),
-            };
-
-            callback(data);
+            callback(parent.get_request());
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_the_saved_filters_while_a_past_search_is_restored() {
+        let settings = Arc::new(Settings::in_memory());
+        settings.set_transportations(&[Transportation::Train]);
+        let filter_defaults = FilterDefaults::new(settings.clone());
+
+        filter_defaults.restoring(|| {
+            filter_defaults.set_transportations(&[Transportation::Bus]);
+            filter_defaults.set_direct_only(true);
         });
+
+        assert_eq!(settings.get_transportations(), vec![Transportation::Train]);
+        assert!(!settings.get_direct_only());
+    }
+
+    #[test]
+    fn it_saves_the_filters_picked_afterwards() {
+        let settings = Arc::new(Settings::in_memory());
+        let filter_defaults = FilterDefaults::new(settings.clone());
+
+        filter_defaults.restoring(|| filter_defaults.set_direct_only(true));
+        filter_defaults.set_transportations(&[Transportation::Tram, Transportation::Bus]);
+        filter_defaults.set_direct_only(true);
+
+        assert_eq!(
+            settings.get_transportations(),
+            vec![Transportation::Tram, Transportation::Bus]
+        );
+        assert!(settings.get_direct_only());
     }
 }
@@ -2,7 +2,7 @@ use chrono::offset::FixedOffset;
 use chrono::DateTime;
 use gtk::prelude::*;
 
-use crate::api::Section;
+use crate::api::{Capacity, Section, Stop};
 
 pub struct SectionWidget {
     pub container: gtk::Grid,
@@ -14,33 +14,35 @@ impl SectionWidget {
         container.set_hexpand(true);
 
         let departure_location = Self::create_label_with_default_margin();
-        departure_location.set_markup(&section.departure.station.name);
+        departure_location.set_markup(&glib::markup_escape_text(&section.departure.station.name));
         departure_location.set_hexpand(true);
         container.attach(&departure_location, 1, 0, 1, 1);
 
         let departure_time = Self::create_label_with_default_margin();
-        departure_time.set_markup(&Self::format_time_with_delay(
+        departure_time.set_markup(&Self::format_time_with_prognosis(
             &section.departure.departure,
+            &section.departure.expected_departure(),
             &section.departure.delay,
         ));
         container.attach(&departure_time, 0, 0, 1, 1);
 
-        let departure_platform = Self::create_platform_label(&section.departure.platform);
+        let departure_platform = Self::create_stop_platform_label(&section.departure);
         container.attach(&departure_platform, 2, 0, 1, 1);
 
         let arrival_location = Self::create_label_with_default_margin();
-        arrival_location.set_markup(&section.arrival.station.name);
+        arrival_location.set_markup(&glib::markup_escape_text(&section.arrival.station.name));
         arrival_location.set_hexpand(true);
         container.attach(&arrival_location, 1, 2, 1, 1);
 
         let arrival_time = Self::create_label_with_default_margin();
-        arrival_time.set_markup(&Self::format_time_with_delay(
+        arrival_time.set_markup(&Self::format_time_with_prognosis(
             &section.arrival.arrival,
+            &section.arrival.expected_arrival(),
             &section.arrival.delay,
         ));
         container.attach(&arrival_time, 0, 2, 1, 1);
 
-        let arrival_platform = Self::create_platform_label(&section.arrival.platform);
+        let arrival_platform = Self::create_stop_platform_label(&section.arrival);
         container.attach(&arrival_platform, 2, 2, 1, 1);
 
         let journey_text = Self::get_journey_text(&section);
@@ -50,12 +52,24 @@ impl SectionWidget {
             container.attach(&journey_name, 1, 1, 1, 1);
         }
 
+        if let Some(journey) = section.journey.as_ref() {
+            if let Some(occupancy) =
+                Self::create_occupancy_box(journey.capacity_1st, journey.capacity_2nd)
+            {
+                container.attach(&occupancy, 2, 1, 1, 1);
+            }
+        }
+
         Self { container }
     }
 
     fn get_journey_text(section: &Section) -> String {
         if let Some(journey) = section.journey.as_ref() {
-            return format!("<i>{} {}</i>", journey.category, journey.number);
+            return format!(
+                "<i>{} {}</i>",
+                glib::markup_escape_text(&journey.category),
+                glib::markup_escape_text(&journey.number)
+            );
         }
 
         if let Some(walk) = section.walk.as_ref() {
@@ -66,6 +80,68 @@ impl SectionWidget {
         "".to_owned()
     }
 
+    fn create_occupancy_box(
+        capacity_1st: Option<Capacity>,
+        capacity_2nd: Option<Capacity>,
+    ) -> Option<gtk::Box> {
+        let capacity = capacity_2nd?;
+
+        let icon_count = match capacity {
+            Capacity::Low => 1,
+            Capacity::Medium => 2,
+            Capacity::High => 3,
+        };
+
+        let container = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        container.set_halign(gtk::Align::Center);
+        container.set_margin_start(5);
+        container.set_margin_end(5);
+
+        for _ in 0..icon_count {
+            let icon =
+                gtk::Image::from_icon_name(Some("avatar-default-symbolic"), gtk::IconSize::Menu);
+            container.add(&icon);
+        }
+
+        let mut tooltip = format!("2nd class: {}", Self::get_capacity_text(capacity));
+        if let Some(capacity) = capacity_1st {
+            tooltip = format!(
+                "1st class: {}\n{}",
+                Self::get_capacity_text(capacity),
+                tooltip
+            );
+        }
+        container.set_tooltip_text(Some(&tooltip));
+
+        Some(container)
+    }
+
+    fn get_capacity_text(capacity: Capacity) -> &'static str {
+        match capacity {
+            Capacity::Low => "low occupancy expected",
+            Capacity::Medium => "medium occupancy expected",
+            Capacity::High => "high occupancy expected",
+        }
+    }
+
+    pub(crate) fn create_stop_platform_label(stop: &Stop) -> gtk::Label {
+        let label = Self::create_platform_label(&stop.platform);
+
+        // A platform change is only announced via the prognosis on some lines
+        if let Some(expected) = stop.expected_platform() {
+            let expected = expected.trim_end_matches('!');
+            let planned = stop.platform.as_deref().map(|p| p.trim_end_matches('!'));
+            if planned != Some(expected) {
+                label.set_markup(&format!(
+                    "<span foreground=\"red\">Pl. {}</span>",
+                    glib::markup_escape_text(expected)
+                ));
+            }
+        }
+
+        label
+    }
+
     fn create_platform_label(platform: &Option<String>) -> gtk::Label {
         let platform = match platform {
             Some(text) => &text,
@@ -73,18 +149,21 @@ impl SectionWidget {
         };
 
         let label = Self::create_label_with_default_margin();
-        label.set_markup(&format!("Pl. {}", platform));
+        label.set_markup(&format!("Pl. {}", glib::markup_escape_text(platform)));
 
         if platform.ends_with("!") {
             let mut platform = platform.to_owned();
             platform.pop();
-            label.set_markup(&format!("<span foreground=\"red\">Pl. {}</span>", platform));
+            label.set_markup(&format!(
+                "<span foreground=\"red\">Pl. {}</span>",
+                glib::markup_escape_text(&platform)
+            ));
         }
 
         label
     }
 
-    fn create_label_with_default_margin() -> gtk::Label {
+    pub(crate) fn create_label_with_default_margin() -> gtk::Label {
         let label = gtk::Label::new(None);
         label.set_margin_top(5);
         label.set_margin_bottom(5);
@@ -93,12 +172,25 @@ impl SectionWidget {
         label
     }
 
-    fn format_time_with_delay(time: &Option<String>, delay: &Option<u16>) -> String {
-        let time = match Self::parse_time(time) {
-            Some(date) => date.format("%H:%M").to_string(),
+    pub(crate) fn format_time_with_prognosis(
+        planned: &Option<DateTime<FixedOffset>>,
+        expected: &Option<DateTime<FixedOffset>>,
+        delay: &Option<u16>,
+    ) -> String {
+        let planned = match planned {
+            Some(date) => date,
             None => return "".to_owned(),
         };
 
+        let time = planned.format("%H:%M").to_string();
+
+        if let Some(expected) = expected {
+            if expected != planned {
+                let expected = expected.format("%H:%M");
+                return format!("{} <span foreground=\"red\">{}</span>", time, expected);
+            }
+        }
+
         let delay: u16 = match delay {
             Some(val) => *val,
             None => 0,
@@ -111,18 +203,4 @@ impl SectionWidget {
 
         return format!("{} <span foreground=\"{}\">+{}</span>", time, color, delay);
     }
-
-    fn parse_time(input: &Option<String>) -> Option<DateTime<FixedOffset>> {
-        let format = "%Y-%m-%dT%H:%M:%S%z";
-
-        let time = match input.as_ref() {
-            Some(time) => time,
-            None => return None,
-        };
-
-        match DateTime::parse_from_str(time, format) {
-            Ok(rfc3339) => Some(rfc3339),
-            Err(_) => None,
-        }
-    }
 }
@@ -0,0 +1,204 @@
+use chrono::prelude::*;
+use gtk::prelude::*;
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::api::{Error, LocationSearch, StationboardEntry, StationboardRequest, TimetableBackend};
+use crate::favorites::Favorites;
+use crate::widgets::*;
+
+const STATIONBOARD_LIMIT: u8 = 20;
+
+enum StationboardMessage {
+    Update(usize, bool, Result<Vec<StationboardEntry>, Error>),
+}
+
+#[derive(Clone)]
+pub struct StationboardWidget {
+    pub container: gtk::Box,
+    station_entry: LocationRowWidget,
+    time_input: TimeRowWidget,
+    transport_filter: TransportFilterWidget,
+    button: gtk::Button,
+    spinner: gtk::Spinner,
+    status_label: gtk::Label,
+    list_box: gtk::ListBox,
+    backend: Arc<dyn TimetableBackend>,
+    sender: glib::Sender<StationboardMessage>,
+    current_request: Arc<Mutex<usize>>,
+}
+
+impl StationboardWidget {
+    pub fn new(
+        favorites: Arc<Favorites>,
+        backend: Arc<dyn TimetableBackend>,
+        location_search: LocationSearch,
+    ) -> Self {
+        let label_size_group = gtk::SizeGroup::new(gtk::SizeGroupMode::Horizontal);
+
+        let fav_box = FavoriteBoxWidget::new(favorites.clone());
+        let station_entry =
+            LocationRowWidget::new("Station", &label_size_group, favorites, location_search);
+        let time_input = TimeRowWidget::new(&label_size_group);
+
+        let transport_filter = TransportFilterWidget::new();
+
+        let button = gtk::ButtonBuilder::new().label("Show").margin(5).build();
+
+        let spinner = gtk::Spinner::new();
+        spinner.set_margin_top(5);
+
+        let status_label = gtk::LabelBuilder::new().margin(5).wrap(true).build();
+
+        let list_box = gtk::ListBox::new();
+        list_box.set_selection_mode(gtk::SelectionMode::None);
+
+        let hadjust: Option<&gtk::Adjustment> = None;
+        let vadjust: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(hadjust, vadjust);
+        scrolled_window.set_vexpand(true);
+        scrolled_window.add(&list_box);
+
+        {
+            let station_entry = station_entry.clone();
+            fav_box.connect_selected(move |favorite| {
+                station_entry.set_text(favorite);
+            });
+        }
+
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        container.add(&fav_box.container);
+        container.add(&station_entry.container);
+        container.add(&time_input.container);
+        container.add(&transport_filter.container);
+        container.add(&button);
+        container.add(&spinner);
+        container.add(&status_label);
+        container.add(&scrolled_window);
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        let widget = Self {
+            container,
+            station_entry,
+            time_input,
+            transport_filter,
+            button,
+            spinner,
+            status_label,
+            list_box,
+            backend,
+            sender,
+            current_request: Arc::new(Mutex::new(0)),
+        };
+
+        widget.setup_event_handlers(receiver);
+
+        widget
+    }
+
+    fn setup_event_handlers(&self, receiver: glib::Receiver<StationboardMessage>) {
+        let parent = self.clone();
+        receiver.attach(None, move |msg| {
+            match msg {
+                StationboardMessage::Update(id, is_arrival, result) => {
+                    // drop responses of requests which were superseded
+                    if id == *parent.current_request.lock().unwrap() {
+                        parent.set_entries(result, is_arrival);
+                    }
+                }
+            }
+
+            glib::Continue(true)
+        });
+
+        let parent = self.clone();
+        self.button.connect_clicked(move |_| {
+            parent.update_stationboard();
+        });
+    }
+
+    fn update_stationboard(&self) {
+        let station = self.station_entry.get_text();
+        if station.is_empty() {
+            return;
+        }
+
+        let request = StationboardRequest {
+            station,
+            datetime: self.get_datetime(),
+            is_arrival: self.time_input.is_arrival_time(),
+            limit: STATIONBOARD_LIMIT,
+            transportations: self.transport_filter.get_transportations(),
+        };
+
+        let id = {
+            let mut current_request = self.current_request.lock().unwrap();
+            *current_request += 1;
+            *current_request
+        };
+
+        self.clear();
+        self.status_label.set_text("");
+        self.spinner.start();
+
+        let backend = self.backend.clone();
+        let sender = self.sender.clone();
+        let is_arrival = request.is_arrival;
+        thread::spawn(move || {
+            let result = backend.stationboard(request);
+            let _ = sender.send(StationboardMessage::Update(id, is_arrival, result));
+        });
+    }
+
+    fn set_entries(&self, result: Result<Vec<StationboardEntry>, Error>, is_arrival: bool) {
+        self.spinner.stop();
+        self.clear();
+
+        let entries = match result {
+            Ok(entries) => entries,
+            Err(error) => {
+                let text = format!("Could not load the stationboard: {}.", error);
+                self.status_label.set_text(&text);
+                return;
+            }
+        };
+
+        if entries.is_empty() {
+            self.status_label
+                .set_text("No departures or arrivals found.");
+        }
+
+        for entry in entries.iter() {
+            let entry_widget = StationboardEntryWidget::new(entry, is_arrival);
+            self.list_box.add(&entry_widget.container);
+        }
+
+        self.list_box.show_all();
+    }
+
+    fn get_datetime(&self) -> Option<String> {
+        let time = self.time_input.get_time()?;
+        let date = match self.time_input.get_date() {
+            Some(date) => date,
+            None => Local::now().format("%Y-%m-%d").to_string(),
+        };
+
+        Some(format!("{} {}", date, time))
+    }
+
+    fn clear(&self) {
+        self.list_box.foreach(|child| {
+            self.list_box.remove(child);
+        });
+    }
+
+    /// Called with `true` when arrivals are chosen instead of departures.
+    pub fn connect_arrival_changed<F>(&self, callback: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        self.time_input.connect_arrival_time_changed(callback);
+    }
+}
@@ -0,0 +1,47 @@
+use gtk::prelude::*;
+
+use crate::api::StationboardEntry;
+use crate::widgets::SectionWidget;
+
+pub struct StationboardEntryWidget {
+    pub container: gtk::Grid,
+}
+
+impl StationboardEntryWidget {
+    pub fn new(entry: &StationboardEntry, is_arrival: bool) -> Self {
+        let container = gtk::Grid::new();
+        container.set_hexpand(true);
+
+        let (planned, expected) = match is_arrival {
+            true => (&entry.stop.arrival, entry.stop.expected_arrival()),
+            false => (&entry.stop.departure, entry.stop.expected_departure()),
+        };
+
+        let time_label = SectionWidget::create_label_with_default_margin();
+        time_label.set_markup(&SectionWidget::format_time_with_prognosis(
+            planned,
+            &expected,
+            &entry.stop.delay,
+        ));
+        container.attach(&time_label, 0, 0, 1, 1);
+
+        let journey_label = SectionWidget::create_label_with_default_margin();
+        journey_label.set_markup(&format!(
+            "<b>{} {}</b>",
+            glib::markup_escape_text(&entry.category),
+            glib::markup_escape_text(&entry.number)
+        ));
+        container.attach(&journey_label, 1, 0, 1, 1);
+
+        let destination_label = SectionWidget::create_label_with_default_margin();
+        destination_label.set_text(&entry.to);
+        destination_label.set_hexpand(true);
+        destination_label.set_xalign(0.0);
+        container.attach(&destination_label, 2, 0, 1, 1);
+
+        let platform_label = SectionWidget::create_stop_platform_label(&entry.stop);
+        container.attach(&platform_label, 3, 0, 1, 1);
+
+        Self { container }
+    }
+}
@@ -1,4 +1,5 @@
 use chrono::prelude::*;
+use gio::prelude::*;
 use gtk::prelude::*;
 
 use std::sync::{Arc, Mutex};
@@ -13,6 +14,7 @@ pub struct TimeRowWidget {
     label: gtk::Label,
     time_picker: DateTimePickerPopover,
     is_arrival_time: Arc<Mutex<bool>>,
+    arrival_time_changed: gio::SimpleAction,
 }
 
 impl TimeRowWidget {
@@ -44,6 +46,7 @@ impl TimeRowWidget {
             label,
             time_picker,
             is_arrival_time: Arc::new(Mutex::new(false)),
+            arrival_time_changed: gio::SimpleAction::new("arrival-time-changed", None),
         };
 
         widget.setup_event_handlers();
@@ -77,6 +80,34 @@ impl TimeRowWidget {
         *self.is_arrival_time.lock().unwrap()
     }
 
+    /// Sets the time to today at `HH:MM` or to now if no time is given.
+    pub fn set_time(&self, time: Option<&str>) {
+        self.set_date_time(None, time);
+    }
+
+    /// Sets the time to `HH:MM` on the `YYYY-MM-DD` date, today if no date is given.
+    pub fn set_date_time(&self, date: Option<&str>, time: Option<&str>) {
+        let date = match date.and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) {
+            Some(date) => Local.from_local_date(&date).single(),
+            None => Some(Local::today()),
+        };
+
+        let time = time
+            .and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok())
+            .zip(date)
+            .and_then(|(t, d)| d.and_time(t));
+
+        self.time_picker.set_date_time(time);
+    }
+
+    pub fn set_arrival_time(&self, is_arrival_time: bool) {
+        *self.is_arrival_time.lock().unwrap() = is_arrival_time;
+
+        self.update_arrival_button_icon();
+        self.update_time_button_label();
+        self.arrival_time_changed.activate(None);
+    }
+
     fn setup_event_handlers(&self) {
         let widget = self.clone();
         self.time_button.connect_clicked(move |_| {
@@ -92,6 +123,7 @@ impl TimeRowWidget {
 
             widget.update_arrival_button_icon();
             widget.update_time_button_label();
+            widget.arrival_time_changed.activate(None);
         });
 
         let widget = self.clone();
@@ -136,4 +168,14 @@ impl TimeRowWidget {
         let icon = gtk::Image::from_icon_name(Some(icon), gtk::IconSize::Menu);
         self.arrival_button.set_image(Some(&icon));
     }
+
+    pub fn connect_arrival_time_changed<F>(&self, callback: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        let is_arrival_time = self.is_arrival_time.clone();
+        self.arrival_time_changed.connect_activate(move |_, _| {
+            callback(*is_arrival_time.lock().unwrap());
+        });
+    }
 }
@@ -0,0 +1,106 @@
+use gio::prelude::*;
+use gtk::prelude::*;
+
+use crate::api::Transportation;
+
+#[derive(Clone)]
+pub struct TransportFilterWidget {
+    pub container: gtk::FlowBox,
+    buttons: Vec<(Transportation, gtk::CheckButton)>,
+    changed: gio::SimpleAction,
+}
+
+impl TransportFilterWidget {
+    pub fn new() -> Self {
+        let container = gtk::FlowBox::new();
+        container.set_selection_mode(gtk::SelectionMode::None);
+        container.set_margin_start(5);
+        container.set_margin_end(5);
+
+        let buttons: Vec<(Transportation, gtk::CheckButton)> = Transportation::ALL
+            .iter()
+            .map(|t| {
+                let button = gtk::CheckButton::with_label(Self::get_label(*t));
+                button.set_active(true);
+                container.add(&button);
+                (*t, button)
+            })
+            .collect();
+
+        let widget = Self {
+            container,
+            buttons,
+            changed: gio::SimpleAction::new("changed", None),
+        };
+
+        widget.setup_event_handlers();
+
+        widget
+    }
+
+    fn setup_event_handlers(&self) {
+        for (_, button) in self.buttons.iter() {
+            let widget = self.clone();
+            button.connect_toggled(move |_| {
+                widget.update_sensitivity();
+                widget.changed.activate(None);
+            });
+        }
+    }
+
+    /// Keeps the last checked mode from being unchecked, as no mode at all
+    /// would be sent as an unfiltered search.
+    fn update_sensitivity(&self) {
+        let active: Vec<&gtk::CheckButton> = self
+            .buttons
+            .iter()
+            .map(|(_, button)| button)
+            .filter(|button| button.get_active())
+            .collect();
+
+        for (_, button) in self.buttons.iter() {
+            button.set_sensitive(active.len() != 1 || !button.get_active());
+        }
+    }
+
+    /// Returns the selected modes or an empty list if all modes are selected.
+    pub fn get_transportations(&self) -> Vec<Transportation> {
+        let selected: Vec<Transportation> = self
+            .buttons
+            .iter()
+            .filter(|(_, button)| button.get_active())
+            .map(|(t, _)| *t)
+            .collect();
+
+        match selected.len() == Transportation::ALL.len() {
+            true => vec![],
+            false => selected,
+        }
+    }
+
+    pub fn set_transportations(&self, transportations: &[Transportation]) {
+        for (transportation, button) in self.buttons.iter() {
+            let is_active = transportations.is_empty() || transportations.contains(transportation);
+            button.set_active(is_active);
+        }
+    }
+
+    pub fn connect_changed<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.changed.connect_activate(move |_, _| {
+            callback();
+        });
+    }
+
+    fn get_label(transportation: Transportation) -> &'static str {
+        match transportation {
+            Transportation::Train => "Train",
+            Transportation::Tram => "Tram",
+            Transportation::Ship => "Ship",
+            Transportation::Bus => "Bus",
+            Transportation::Cableway => "Cableway",
+        }
+    }
+}
@@ -2,6 +2,7 @@ use gtk::prelude::*;
 
 use std::sync::{Arc, Mutex};
 
+use crate::api::{Coordinate, Location, LocationSearch};
 use crate::favorites::Favorites;
 use crate::widgets::LocationRowWidget;
 
@@ -10,18 +11,26 @@ pub struct ViaBoxWidget {
     pub container: gtk::Box,
     label_size_group: gtk::SizeGroup,
     favorites: Arc<Favorites>,
+    location_search: LocationSearch,
     vias: Arc<Mutex<Vec<LocationRowWidget>>>,
+    reference: Arc<Mutex<Option<Coordinate>>>,
 }
 
 impl ViaBoxWidget {
-    pub fn new(label_size_group: &gtk::SizeGroup, favorites: Arc<Favorites>) -> Self {
+    pub fn new(
+        label_size_group: &gtk::SizeGroup,
+        favorites: Arc<Favorites>,
+        location_search: LocationSearch,
+    ) -> Self {
         let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
 
         let widget = Self {
             container,
             label_size_group: label_size_group.clone(),
             favorites,
+            location_search,
             vias: Arc::new(Mutex::new(vec![])),
+            reference: Arc::new(Mutex::new(None)),
         };
 
         widget.add_entry(None);
@@ -41,7 +50,13 @@ impl ViaBoxWidget {
     }
 
     fn create_entry(&self, location: Option<&str>) -> LocationRowWidget {
-        let entry = LocationRowWidget::new("Via", &self.label_size_group, self.favorites.clone());
+        let entry = LocationRowWidget::new(
+            "Via",
+            &self.label_size_group,
+            self.favorites.clone(),
+            self.location_search.clone(),
+        );
+        entry.set_reference_coordinate(self.reference.lock().unwrap().clone());
 
         let vias = self.vias.clone();
         let widget = entry.clone();
@@ -85,6 +100,20 @@ impl ViaBoxWidget {
         self.get_vias_matching(|via| via.is_empty() == false)
     }
 
+    /// The vias which were picked from the suggestions.
+    pub fn get_locations(&self) -> Vec<Location> {
+        let vias = self.vias.lock().unwrap();
+        vias.iter().filter_map(|via| via.get_location()).collect()
+    }
+
+    pub fn set_reference_coordinate(&self, coordinate: Option<Coordinate>) {
+        for via in self.vias.lock().unwrap().iter() {
+            via.set_reference_coordinate(coordinate.clone());
+        }
+
+        *self.reference.lock().unwrap() = coordinate;
+    }
+
     fn needs_reorder(&self) -> bool {
         let vias = self.vias.clone();
         let vias = vias.lock().unwrap();
@@ -110,6 +139,33 @@ impl ViaBoxWidget {
         }
     }
 
+    /// Replaces all via rows with the given locations.
+    pub fn set_vias(&self, locations: &[String]) {
+        {
+            let mut vias = self.vias.lock().unwrap();
+            for via in vias.drain(..) {
+                self.container.remove(&via.container);
+            }
+        }
+
+        self.add_entry(None);
+
+        for location in locations {
+            // filling the last row makes the change handler append an empty one
+            let last = self.vias.lock().unwrap().last().cloned();
+            if let Some(row) = last {
+                row.set_text(location);
+            }
+        }
+    }
+
+    pub fn find_location(&self, text: &str) -> Option<LocationRowWidget> {
+        let vias = self.vias.clone();
+        let vias = vias.lock().unwrap();
+
+        vias.iter().find(|via| via.get_text() == text).cloned()
+    }
+
     fn get_vias_matching<F>(&self, filter: F) -> Vec<String>
     where
         F: Fn(&str) -> bool,
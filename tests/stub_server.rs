@@ -0,0 +1,66 @@
+use sbb::api::{
+    Error, OpendataBackend, SearchConnectionRequest, StationboardRequest, StubServer,
+    TimetableBackend,
+};
+
+fn backend() -> OpendataBackend {
+    let fixture_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/api");
+    let server = StubServer::bind("127.0.0.1:0", fixture_dir).unwrap();
+    let base_url = server.base_url().unwrap();
+    server.spawn();
+
+    OpendataBackend::with_base_url(&base_url)
+}
+
+#[test]
+fn it_uses_the_configured_base_url() {
+    let backend = OpendataBackend::with_base_url("http://localhost:1234/v1/");
+    assert_eq!(backend.base_url(), "http://localhost:1234/v1");
+}
+
+#[test]
+fn it_fetches_locations_from_the_stub_server() {
+    let stations = backend().search_location("Basel").unwrap();
+    assert!(stations.iter().any(|l| l.name == "Basel SBB"));
+}
+
+#[test]
+fn it_fetches_nearby_stations_from_the_stub_server() {
+    let stations = backend().search_nearby(47.547408, 7.589547).unwrap();
+    assert_eq!(stations[0].name, "Basel SBB");
+}
+
+#[test]
+fn it_fetches_connections_from_the_stub_server() {
+    let request = SearchConnectionRequest {
+        from: "Zug".to_string(),
+        to: "Chur".to_string(),
+        is_arrival_time: true,
+        ..Default::default()
+    };
+
+    let connections = backend().search_connection(request).unwrap();
+    assert!(!connections.is_empty());
+}
+
+#[test]
+fn it_fetches_the_stationboard_from_the_stub_server() {
+    let request = StationboardRequest {
+        station: "Bern".to_string(),
+        datetime: None,
+        is_arrival: false,
+        limit: 10,
+        transportations: vec![],
+    };
+
+    let entries = backend().stationboard(request).unwrap();
+    assert!(!entries.is_empty());
+}
+
+#[test]
+fn it_reports_http_status_errors() {
+    match backend().search_location("Unknown") {
+        Err(Error::Status(status)) => assert_eq!(status, 404),
+        other => panic!("expected a HTTP error, got {:?}", other),
+    }
+}